@@ -1,3 +1,74 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
 fn main() {
+    check_shield_manifest();
     tauri_build::build()
 }
+
+// ================================================================
+// MANIFEST DEL SHIELD — scripts/manifest.txt
+// ================================================================
+// En release se embeben las copias de scripts/ con include_str!. Antes
+// se comprueba que coinciden con el manifest, para que ningún cambio
+// en un módulo llegue a release sin pasar por él.
+// ================================================================
+
+/// FNV-1a de 64 bits sin los \r: el checkout en Windows no cambia el hash.
+fn fnv1a(text: &str) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in text.bytes().filter(|&b| b != b'\r') {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    hash
+}
+
+fn check_shield_manifest() {
+    let dir = Path::new("scripts");
+    println!("cargo:rerun-if-changed=scripts");
+    if std::env::var("PROFILE").as_deref() != Ok("release") {
+        return;
+    }
+
+    let manifest = fs::read_to_string(dir.join("manifest.txt"))
+        .unwrap_or_else(|e| panic!("scripts/manifest.txt: {e}"));
+
+    let mut listed = HashSet::new();
+    let mut stale = Vec::new();
+    for line in manifest
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+    {
+        let Some((expected, file)) = line.split_once(char::is_whitespace) else {
+            panic!("scripts/manifest.txt: línea inválida: {line}");
+        };
+        let file = file.trim();
+        listed.insert(file.to_string());
+
+        let source = fs::read_to_string(dir.join(file))
+            .unwrap_or_else(|e| panic!("scripts/{file}: {e}"));
+        let actual = format!("{:016x}", fnv1a(&source));
+        if actual != expected {
+            stale.push(format!("{actual}  {file}"));
+        }
+    }
+
+    // Un módulo nuevo también tiene que estar en el manifest
+    for entry in fs::read_dir(dir).into_iter().flatten().flatten() {
+        let file = entry.file_name().to_string_lossy().to_string();
+        if file.ends_with(".js") && !listed.contains(&file) {
+            let source = fs::read_to_string(entry.path()).unwrap_or_default();
+            stale.push(format!("{:016x}  {file}", fnv1a(&source)));
+        }
+    }
+
+    if !stale.is_empty() {
+        panic!(
+            "scripts/manifest.txt no coincide con los módulos del shield:\n{}",
+            stale.join("\n")
+        );
+    }
+}
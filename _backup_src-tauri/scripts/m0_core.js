@@ -33,8 +33,9 @@ window.__atomShield = window.__atomShield || {};
     AS.noopObj = function () { return {}; };
 
     // --- Site detection ---
+    // Which modules run on which host is decided in Rust (shield.rs);
+    // the injected script checks each module against this frame's host.
     AS.host = (window.location.hostname || '').toLowerCase();
-    AS.isYT = AS.host.includes('youtube.com');
 
     // --- Safe CSS injection (Trusted Types compatible) ---
     AS.injectCSS = function (css) {
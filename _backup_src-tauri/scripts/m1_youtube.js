@@ -6,7 +6,7 @@
     'use strict';
 
     var AS = window.__atomShield;
-    if (!AS || AS.skip || !AS.isYT) return;
+    if (!AS || AS.skip) return;
 
     // --- Strip ad data from any object ---
     function stripAds(o) {
@@ -13,7 +13,7 @@
     'use strict';
 
     var AS = window.__atomShield;
-    if (!AS || AS.skip || !AS.isYT) return;
+    if (!AS || AS.skip) return;
 
     // --- CSS: Hide ad elements OUTSIDE the player ---
     // These are safe because YouTube doesn't monitor them
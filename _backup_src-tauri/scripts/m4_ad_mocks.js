@@ -10,7 +10,7 @@
     'use strict';
 
     var AS = window.__atomShield;
-    if (!AS || AS.skip || AS.isYT) return;
+    if (!AS || AS.skip) return;
 
     var noop = AS.noop;
     var noopArray = AS.noopArray;
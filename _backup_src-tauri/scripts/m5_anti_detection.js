@@ -12,7 +12,7 @@
     'use strict';
 
     var AS = window.__atomShield;
-    if (!AS || AS.skip || AS.isYT) return;
+    if (!AS || AS.skip) return;
 
     // --- FuckAdBlock / BlockAdBlock ---
     var fabMock = {
@@ -1,3 +1,38 @@
+// ================================================================
+// POPUP BLOCKER — compartido por todas las plataformas
+// ================================================================
+// El bloqueo de red solo existe en WebView2, pero las ventanas nuevas
+// (window.open / target=_blank) se filtran en cualquier plataforma.
+// ================================================================
+
+pub mod popup_blocker {
+    // --- Popups y redirects ---
+    const POPUP_DOMAINS: &[&str] = &[
+        "popads.net",
+        "popcash.net",
+        "propellerads.com",
+        "popmyads.com",
+        "popunderjs.com",
+        "popuptraffic.com",
+        "richpush.co",
+        "push.express",
+        "pushpush.net",
+        "pushame.com",
+        "pushengage.com",
+        "sendpulse.com",
+        "gravitec.net",
+        "pushassist.com",
+        "subscribers.com",
+        "izooto.com",
+    ];
+
+    /// Coincidencia por subcadena, igual que el resto de listas.
+    pub fn is_popup_domain(url: &str) -> bool {
+        let lower = url.to_lowercase();
+        POPUP_DOMAINS.iter().any(|d| lower.contains(d))
+    }
+}
+
 #[cfg(target_os = "windows")]
 pub mod network_blocker {
     use tauri::Webview;
@@ -387,26 +422,6 @@ pub mod network_blocker {
         "xyzads.com",
     ];
 
-    // --- Popups y redirects ---
-    const POPUP_DOMAINS: &[&str] = &[
-        "popads.net",
-        "popcash.net",
-        "propellerads.com",
-        "popmyads.com",
-        "popunderjs.com",
-        "popuptraffic.com",
-        "richpush.co",
-        "push.express",
-        "pushpush.net",
-        "pushame.com",
-        "pushengage.com",
-        "sendpulse.com",
-        "gravitec.net",
-        "pushassist.com",
-        "subscribers.com",
-        "izooto.com",
-    ];
-
     // --- CNAME cloaking domains (trackers que se disfrazan de first-party) ---
     const CNAME_TRACKERS: &[&str] = &[
         "adobedc.net",
@@ -536,10 +551,8 @@ pub mod network_blocker {
         }
 
         // 7. Popups
-        for d in POPUP_DOMAINS {
-            if lower.contains(d) {
-                return true;
-            }
+        if super::popup_blocker::is_popup_domain(&lower) {
+            return true;
         }
 
         // 8. CNAME trackers
@@ -0,0 +1,75 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use crate::history::now_millis;
+
+// ================================================================
+// MARCADORES — <perfil>/bookmarks.json
+// ================================================================
+// Antes vivían en el localStorage de cada ventana; ahora los comparten
+// todas y el buscador rápido los ve. Cada cambio se avisa a todas las
+// ventanas con "bookmarks-changed".
+// ================================================================
+
+const BOOKMARKS_FILE: &str = "bookmarks.json";
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Bookmark {
+    pub url: String,
+    #[serde(default)]
+    pub title: String,
+    /// Milisegundos desde 1970, como Date.now()
+    #[serde(default)]
+    pub added: u64,
+}
+
+pub struct Bookmarks {
+    path: PathBuf,
+    /// El más reciente primero
+    list: Vec<Bookmark>,
+}
+
+impl Bookmarks {
+    pub fn load(profile: PathBuf) -> Self {
+        let path = profile.join(BOOKMARKS_FILE);
+        let list = fs::read_to_string(&path)
+            .ok()
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default();
+        Self { path, list }
+    }
+
+    fn save(&self) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(&self.list).map_err(|e| e.to_string())?;
+        fs::write(&self.path, json).map_err(|e| e.to_string())
+    }
+
+    pub fn list(&self) -> &[Bookmark] {
+        &self.list
+    }
+
+    /// Si ya estaba solo cambia el título
+    pub fn add(&mut self, url: String, title: String) -> Result<(), String> {
+        match self.list.iter_mut().find(|b| b.url == url) {
+            Some(bookmark) => bookmark.title = title,
+            None => self.list.insert(
+                0,
+                Bookmark {
+                    url,
+                    title,
+                    added: now_millis(),
+                },
+            ),
+        }
+        self.save()
+    }
+
+    pub fn remove(&mut self, url: &str) -> Result<(), String> {
+        self.list.retain(|b| b.url != url);
+        self.save()
+    }
+}
+
+pub type BookmarkState = Arc<Mutex<Bookmarks>>;
@@ -0,0 +1,191 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use crate::shield::host_matches;
+
+// ================================================================
+// CONTENEDORES — <perfil>/containers.json
+// ================================================================
+// Cada contenedor tiene su propio directorio de datos del webview
+// (<perfil>/containers/<id>): cookies, caché y localStorage aparte, para
+// tener abiertas a la vez varias cuentas del mismo sitio. Las pestañas
+// abiertas desde una de contenedor se quedan en él, y las reglas por
+// host mandan un sitio siempre a un contenedor.
+// ================================================================
+
+const CONTAINERS_FILE: &str = "containers.json";
+const CONTAINERS_DIR: &str = "containers";
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ContainerColor {
+    Blue,
+    Turquoise,
+    Green,
+    Yellow,
+    Orange,
+    Red,
+    Pink,
+    Purple,
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub struct Container {
+    pub id: String,
+    pub name: String,
+    pub color: ContainerColor,
+}
+
+/// `host` y sus subdominios se abren siempre en `container`
+#[derive(Clone, Serialize, Deserialize)]
+pub struct HostRule {
+    pub host: String,
+    pub container: String,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+struct ContainersFile {
+    containers: Vec<Container>,
+    rules: Vec<HostRule>,
+    /// Los ids no se reutilizan: el de uno borrado puede seguir en la sesión
+    counter: u32,
+}
+
+pub struct Containers {
+    profile: PathBuf,
+    file: ContainersFile,
+}
+
+impl Containers {
+    pub fn load(profile: PathBuf) -> Self {
+        let file = fs::read_to_string(profile.join(CONTAINERS_FILE))
+            .ok()
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default();
+        Self { profile, file }
+    }
+
+    fn save(&self) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(&self.file).map_err(|e| e.to_string())?;
+        fs::write(self.profile.join(CONTAINERS_FILE), json).map_err(|e| e.to_string())
+    }
+
+    pub fn list(&self) -> Vec<Container> {
+        self.file.containers.clone()
+    }
+
+    pub fn get(&self, id: &str) -> Option<&Container> {
+        self.file.containers.iter().find(|c| c.id == id)
+    }
+
+    pub fn data_dir(&self, id: &str) -> PathBuf {
+        self.profile.join(CONTAINERS_DIR).join(id)
+    }
+
+    pub fn create(&mut self, name: String, color: ContainerColor) -> Result<Container, String> {
+        self.file.counter += 1;
+        let container = Container {
+            id: format!("container-{}", self.file.counter),
+            name,
+            color,
+        };
+        self.file.containers.push(container.clone());
+        self.save()?;
+        Ok(container)
+    }
+
+    pub fn update(
+        &mut self,
+        id: &str,
+        name: String,
+        color: ContainerColor,
+    ) -> Result<Container, String> {
+        let container = self
+            .file
+            .containers
+            .iter_mut()
+            .find(|c| c.id == id)
+            .ok_or("Container not found")?;
+        container.name = name;
+        container.color = color;
+        let container = container.clone();
+        self.save()?;
+        Ok(container)
+    }
+
+    /// Borra el contenedor, sus reglas y sus datos. Con pestañas abiertas
+    /// en él no hay que llamarlo: el motor tiene sus ficheros en uso.
+    pub fn remove(&mut self, id: &str) -> Result<(), String> {
+        let index = self
+            .file
+            .containers
+            .iter()
+            .position(|c| c.id == id)
+            .ok_or("Container not found")?;
+        self.file.containers.remove(index);
+        self.file.rules.retain(|r| r.container != id);
+        self.save()?;
+        let _ = fs::remove_dir_all(self.data_dir(id));
+        Ok(())
+    }
+
+    pub fn rules(&self) -> Vec<HostRule> {
+        self.file.rules.clone()
+    }
+
+    /// Una regla por host: la nueva sustituye a la que hubiera.
+    pub fn set_rule(&mut self, host: &str, container: &str) -> Result<(), String> {
+        if self.get(container).is_none() {
+            return Err("Container not found".to_string());
+        }
+        let host = normalize_host(host).ok_or("Invalid host")?;
+        self.file.rules.retain(|r| r.host != host);
+        self.file.rules.push(HostRule {
+            host,
+            container: container.to_string(),
+        });
+        self.save()
+    }
+
+    pub fn remove_rule(&mut self, host: &str) -> Result<(), String> {
+        let host = normalize_host(host).ok_or("Invalid host")?;
+        self.file.rules.retain(|r| r.host != host);
+        self.save()
+    }
+
+    /// Contenedor al que manda una regla para `url`. Si varias encajan
+    /// gana la del host más concreto.
+    pub fn for_url(&self, url: &str) -> Option<&Container> {
+        let url = tauri::Url::parse(url).ok()?;
+        let host = url.host_str()?;
+        self.file
+            .rules
+            .iter()
+            .filter(|r| host_matches(&r.host, host))
+            .max_by_key(|r| r.host.len())
+            .and_then(|r| self.get(&r.container))
+    }
+}
+
+/// "https://Jira.Example.com/x", "jira.example.com:8080" o
+/// "jira.example.com" → "jira.example.com". Las reglas van por host: el
+/// puerto no cuenta.
+fn normalize_host(host: &str) -> Option<String> {
+    let host = host.trim().to_ascii_lowercase();
+    let mut host = match tauri::Url::parse(&host) {
+        Ok(url) if url.host_str().is_some() => url.host_str()?.to_string(),
+        _ => host,
+    };
+    if let Some((name, port)) = host.rsplit_once(':') {
+        if !name.contains(':') && !port.is_empty() && port.bytes().all(|b| b.is_ascii_digit()) {
+            host = name.to_string();
+        }
+    }
+    let host = host.trim_matches('.').to_string();
+    (!host.is_empty() && !host.contains(['/', ' '])).then_some(host)
+}
+
+pub type ContainerState = Arc<Mutex<Containers>>;
@@ -0,0 +1,142 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use crate::shield::{self, Phase, RunAt};
+
+// ================================================================
+// DIAGNÓSTICO DEL SHIELD — comando shield_report
+// ================================================================
+// Cada módulo avisa al terminar (o al lanzar una excepción) con su id,
+// la URL del frame y la fase por la que llegó. Se agrupa por pestaña y
+// se vacía al empezar a cargar un documento nuevo (PageLoadEvent::Started).
+//
+// Los avisos salen del mundo de la página, así que la propia página puede
+// suprimirlos o inventarlos. Aquí solo se descartan los imposibles (un
+// módulo que no aplica a ese frame, un "documento principal" de otro
+// origen); el resultado es orientativo, no una prueba de que el shield
+// se ejecutó.
+// ================================================================
+
+/// Tope por documento: hay páginas con cientos de iframes
+const MAX_REPORTS: usize = 500;
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ShieldReport {
+    module: String,
+    frame_url: String,
+    /// Documento principal (window.top === window)
+    top: bool,
+    phase: Phase,
+    error: Option<String>,
+}
+
+impl ShieldReport {
+    /// Coherente con lo que se inyectó: el módulo aplica al frame y, si
+    /// dice venir del documento principal, es del origen de la pestaña.
+    pub fn is_plausible(&self, tab_url: &str) -> bool {
+        let applies = shield::modules_for(&self.frame_url)
+            .iter()
+            .any(|m| m.id == self.module);
+        let same_origin = || match (
+            tauri::Url::parse(&self.frame_url),
+            tauri::Url::parse(tab_url),
+        ) {
+            (Ok(frame), Ok(tab)) => frame.origin() == tab.origin(),
+            _ => false,
+        };
+        applies && (!self.top || same_origin())
+    }
+}
+
+#[derive(Serialize)]
+pub struct TabDiagnostics {
+    tab_id: String,
+    url: String,
+    /// Módulos que deberían ejecutarse en el documento principal
+    expected: Vec<String>,
+    /// Esperados sin ningún aviso del documento principal
+    missing: Vec<String>,
+    /// Módulos de inicio de documento que solo llegaron por el respaldo
+    fallback_only: Vec<String>,
+    /// Con alguna excepción, en cualquier frame
+    failing: Vec<String>,
+    reports: Vec<ShieldReport>,
+}
+
+pub struct ShieldDiagnostics {
+    tabs: HashMap<String, Vec<ShieldReport>>,
+}
+
+impl ShieldDiagnostics {
+    pub fn new() -> Self {
+        Self {
+            tabs: HashMap::new(),
+        }
+    }
+
+    /// Documento nuevo en la pestaña: los avisos anteriores ya no valen
+    pub fn reset(&mut self, tab_id: &str) {
+        self.tabs.insert(tab_id.to_string(), Vec::new());
+    }
+
+    pub fn record(&mut self, tab_id: &str, report: ShieldReport) {
+        let reports = self.tabs.entry(tab_id.to_string()).or_default();
+        // Un aviso por módulo, frame y fase: el último manda
+        if let Some(existing) = reports.iter_mut().find(|r| {
+            r.module == report.module && r.frame_url == report.frame_url && r.phase == report.phase
+        }) {
+            *existing = report;
+        } else if reports.len() < MAX_REPORTS {
+            reports.push(report);
+        }
+    }
+
+    pub fn forget_tab(&mut self, tab_id: &str) {
+        self.tabs.remove(tab_id);
+    }
+
+    pub fn summary(&self, tab_id: &str, url: &str) -> TabDiagnostics {
+        let reports = self.tabs.get(tab_id).cloned().unwrap_or_default();
+        let modules = shield::modules_for(url);
+
+        let top_phases = |id: &str| -> Vec<Phase> {
+            reports
+                .iter()
+                .filter(|r| r.top && r.module == id)
+                .map(|r| r.phase)
+                .collect()
+        };
+
+        let mut missing = Vec::new();
+        let mut fallback_only = Vec::new();
+        for module in &modules {
+            let phases = top_phases(module.id);
+            if phases.is_empty() {
+                missing.push(module.id.to_string());
+            } else if module.run_at == RunAt::DocumentStart && !phases.contains(&Phase::Early) {
+                fallback_only.push(module.id.to_string());
+            }
+        }
+
+        let mut failing: Vec<String> = reports
+            .iter()
+            .filter(|r| r.error.is_some())
+            .map(|r| r.module.clone())
+            .collect();
+        failing.sort();
+        failing.dedup();
+
+        TabDiagnostics {
+            tab_id: tab_id.to_string(),
+            url: url.to_string(),
+            expected: modules.iter().map(|m| m.id.to_string()).collect(),
+            missing,
+            fallback_only,
+            failing,
+            reports,
+        }
+    }
+}
+
+pub type DiagnosticsState = Arc<Mutex<ShieldDiagnostics>>;
@@ -0,0 +1,245 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::tabs::{BrowserWindow, Tab, TabManager};
+
+// ================================================================
+// DESCARTE DE PESTAÑAS — <perfil>/discard.json
+// ================================================================
+// Una pestaña descartada pierde su webview y queda solo su Tab en el
+// TabManager, igual que las restauradas sin cargar: se vuelve a cargar
+// al activarla. Nunca se descartan las que están a la vista (también en
+// su ventana flotante), las fijadas, las que suenan ni las que tienen un
+// formulario a medio rellenar.
+// ================================================================
+
+const POLICY_FILE: &str = "discard.json";
+/// Cada cuánto se revisan las pestañas
+pub const CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DiscardPolicy {
+    pub enabled: bool,
+    /// Minutos sin activar tras los que se descarta (0 = nunca por tiempo)
+    pub idle_minutes: u64,
+    /// Webviews vivos como mucho; por encima se descartan las de fondo
+    /// más antiguas (0 = sin límite)
+    pub max_live_tabs: usize,
+    /// Memoria de Atom y del motor en MB; por encima se descartan todas
+    /// las de fondo (0 = sin límite)
+    pub max_memory_mb: u64,
+}
+
+impl Default for DiscardPolicy {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            idle_minutes: 30,
+            max_live_tabs: 20,
+            max_memory_mb: 0,
+        }
+    }
+}
+
+pub struct Discarder {
+    path: PathBuf,
+    policy: DiscardPolicy,
+}
+
+impl Discarder {
+    pub fn load(profile: PathBuf) -> Self {
+        let path = profile.join(POLICY_FILE);
+        let policy = fs::read_to_string(&path)
+            .ok()
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default();
+        Self { path, policy }
+    }
+
+    pub fn policy(&self) -> DiscardPolicy {
+        self.policy.clone()
+    }
+
+    pub fn set_policy(&mut self, policy: DiscardPolicy) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(&policy).map_err(|e| e.to_string())?;
+        fs::write(&self.path, json).map_err(|e| e.to_string())?;
+        self.policy = policy;
+        Ok(())
+    }
+
+    /// ¿Hace falta medir la memoria en esta pasada?
+    pub fn watches_memory(&self) -> bool {
+        self.policy.enabled && self.policy.max_memory_mb > 0
+    }
+
+    /// Pestañas a descartar ahora, de la que lleva más tiempo sin
+    /// activarse a la que menos.
+    pub fn candidates(
+        &self,
+        manager: &TabManager,
+        memory_mb: Option<u64>,
+        now: Instant,
+    ) -> Vec<String> {
+        let policy = &self.policy;
+        if !policy.enabled {
+            return Vec::new();
+        }
+
+        // Las que se pueden descartar (ver can_discard), la que lleva
+        // más tiempo sin activarse primero
+        let mut eligible: Vec<_> = manager
+            .windows
+            .iter()
+            .flat_map(|w| w.tabs.iter().filter(move |t| can_discard(w, t)))
+            .collect();
+        eligible.sort_by_key(|t| t.last_active);
+
+        let live = manager.tabs().filter(|t| !t.discarded).count();
+        let over_memory =
+            policy.max_memory_mb > 0 && memory_mb.is_some_and(|used| used > policy.max_memory_mb);
+        let mut excess = if over_memory {
+            eligible.len()
+        } else if policy.max_live_tabs > 0 {
+            live.saturating_sub(policy.max_live_tabs)
+        } else {
+            0
+        };
+        let idle = Duration::from_secs(policy.idle_minutes * 60);
+
+        eligible
+            .into_iter()
+            .filter(|t| {
+                if excess > 0 {
+                    excess -= 1;
+                    return true;
+                }
+                policy.idle_minutes > 0 && now.duration_since(t.last_active) >= idle
+            })
+            .map(|t| t.id.clone())
+            .collect()
+    }
+}
+
+// ================================================================
+// MEMORIA — Atom más los procesos del motor, que son hijos suyos
+// (WebKitWebProcess en Linux, msedgewebview2 en Windows)
+// ================================================================
+
+pub fn memory_mb() -> Option<u64> {
+    let tree = process_tree();
+    let mut pids = vec![std::process::id()];
+    let mut i = 0;
+    while i < pids.len() {
+        let parent = pids[i];
+        for &(pid, ppid) in &tree {
+            if ppid == parent && !pids.contains(&pid) {
+                pids.push(pid);
+            }
+        }
+        i += 1;
+    }
+    let bytes: u64 = pids.iter().filter_map(|&pid| resident_bytes(pid)).sum();
+    (bytes > 0).then_some(bytes / (1024 * 1024))
+}
+
+/// (pid, pid del padre) de todos los procesos
+#[cfg(target_os = "linux")]
+fn process_tree() -> Vec<(u32, u32)> {
+    let Ok(entries) = fs::read_dir("/proc") else {
+        return Vec::new();
+    };
+    entries
+        .flatten()
+        .filter_map(|entry| {
+            let pid: u32 = entry.file_name().to_str()?.parse().ok()?;
+            let stat = fs::read_to_string(entry.path().join("stat")).ok()?;
+            // El nombre va entre paréntesis y puede tener espacios
+            let (_, rest) = stat.rsplit_once(')')?;
+            let ppid = rest.split_whitespace().nth(1)?.parse().ok()?;
+            Some((pid, ppid))
+        })
+        .collect()
+}
+
+#[cfg(target_os = "linux")]
+fn resident_bytes(pid: u32) -> Option<u64> {
+    // En kB, sin depender del tamaño de página como statm
+    let status = fs::read_to_string(format!("/proc/{pid}/status")).ok()?;
+    let line = status.lines().find(|l| l.starts_with("VmRSS:"))?;
+    let kb: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kb * 1024)
+}
+
+#[cfg(target_os = "windows")]
+fn process_tree() -> Vec<(u32, u32)> {
+    use windows::Win32::Foundation::CloseHandle;
+    use windows::Win32::System::Diagnostics::ToolHelp::{
+        CreateToolhelp32Snapshot, Process32FirstW, Process32NextW, PROCESSENTRY32W,
+        TH32CS_SNAPPROCESS,
+    };
+
+    let mut tree = Vec::new();
+    unsafe {
+        let Ok(snapshot) = CreateToolhelp32Snapshot(TH32CS_SNAPPROCESS, 0) else {
+            return tree;
+        };
+        let mut entry = PROCESSENTRY32W {
+            dwSize: std::mem::size_of::<PROCESSENTRY32W>() as u32,
+            ..Default::default()
+        };
+        let mut more = Process32FirstW(snapshot, &mut entry).is_ok();
+        while more {
+            tree.push((entry.th32ProcessID, entry.th32ParentProcessID));
+            more = Process32NextW(snapshot, &mut entry).is_ok();
+        }
+        let _ = CloseHandle(snapshot);
+    }
+    tree
+}
+
+#[cfg(target_os = "windows")]
+fn resident_bytes(pid: u32) -> Option<u64> {
+    use windows::Win32::Foundation::CloseHandle;
+    use windows::Win32::System::ProcessStatus::{GetProcessMemoryInfo, PROCESS_MEMORY_COUNTERS};
+    use windows::Win32::System::Threading::{OpenProcess, PROCESS_QUERY_LIMITED_INFORMATION};
+
+    unsafe {
+        let process = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, pid).ok()?;
+        let mut counters = PROCESS_MEMORY_COUNTERS::default();
+        let read = GetProcessMemoryInfo(
+            process,
+            &mut counters,
+            std::mem::size_of::<PROCESS_MEMORY_COUNTERS>() as u32,
+        );
+        let _ = CloseHandle(process);
+        read.ok()?;
+        Some(counters.WorkingSetSize as u64)
+    }
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "linux")))]
+fn process_tree() -> Vec<(u32, u32)> {
+    Vec::new()
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "linux")))]
+fn resident_bytes(_pid: u32) -> Option<u64> {
+    None
+}
+
+pub type DiscardState = Arc<Mutex<Discarder>>;
+
+/// Fuera de la vista y sin nada que perder. discard_tab lo vuelve a
+/// comprobar justo antes de cerrar el webview.
+pub fn can_discard(window: &BrowserWindow, tab: &Tab) -> bool {
+    !window.visible().contains(&tab.id)
+        && !tab.discarded
+        && !tab.pinned
+        && !tab.pip
+        && !tab.audible
+        && !tab.form_dirty
+}
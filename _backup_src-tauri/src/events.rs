@@ -0,0 +1,63 @@
+use serde::Serialize;
+
+use crate::tabs::Tab;
+
+// ================================================================
+// EVENTOS DE PESTAÑAS — tab-created, tab-closed, tab-activated, tab-moved
+// ================================================================
+// Salen del backend venga de donde venga el cambio (la UI, un popup, la
+// sesión, otra ventana), a la ventana de la pestaña y siempre con la
+// pestaña entera: con ellos y "tab-updated" cualquier UI puede rehacer
+// su vista sin llevar su propia lista.
+// ================================================================
+
+#[derive(Clone, Serialize)]
+#[serde(untagged)]
+pub enum TabEvent {
+    Created {
+        tab: Tab,
+        window: String,
+        /// Posición en la barra de su ventana
+        index: usize,
+        active: bool,
+    },
+    Closed {
+        tab: Tab,
+        window: String,
+    },
+    Activated {
+        tab: Tab,
+        window: String,
+        /// La que era la activa de la ventana, si sigue abierta
+        previous: Option<String>,
+    },
+    /// En la misma ventana (`from` == `to`) o a otra
+    Moved {
+        tab: Tab,
+        from: String,
+        to: String,
+        index: usize,
+    },
+}
+
+impl TabEvent {
+    pub fn name(&self) -> &'static str {
+        match self {
+            TabEvent::Created { .. } => "tab-created",
+            TabEvent::Closed { .. } => "tab-closed",
+            TabEvent::Activated { .. } => "tab-activated",
+            TabEvent::Moved { .. } => "tab-moved",
+        }
+    }
+
+    /// Ventanas a las que se avisa: las dos si cambia de ventana
+    pub fn windows(&self) -> Vec<&str> {
+        match self {
+            TabEvent::Created { window, .. }
+            | TabEvent::Closed { window, .. }
+            | TabEvent::Activated { window, .. } => vec![window.as_str()],
+            TabEvent::Moved { from, to, .. } if from == to => vec![from.as_str()],
+            TabEvent::Moved { from, to, .. } => vec![from.as_str(), to.as_str()],
+        }
+    }
+}
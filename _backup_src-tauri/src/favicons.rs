@@ -0,0 +1,299 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::history::now_millis;
+
+// ================================================================
+// FAVICONS — <perfil>/favicons/
+// ================================================================
+// Los baja la propia pestaña (FAVICON_SCRIPT en lib.rs): sus cookies, su
+// contenedor y su red, sin preguntar a ningún servicio de fuera. Llegan
+// ya como PNG de ICON_SIZE píxeles y se guardan por contenido
+// (<hash>.png), así que los sitios con el mismo icono comparten fichero;
+// index.json dice qué icono tiene cada host y cuándo se bajó.
+// ================================================================
+
+const FAVICONS_DIR: &str = "favicons";
+const INDEX_FILE: &str = "index.json";
+/// Lado del PNG que devuelve FAVICON_SCRIPT
+pub const ICON_SIZE: u32 = 32;
+/// Pasado esto se vuelve a pedir; mientras tanto vale el de la caché
+const REFRESH_AFTER_MS: u64 = 7 * 24 * 3600 * 1000;
+/// Pasado esto se olvida, aunque no se haya podido volver a pedir
+const EXPIRE_AFTER_MS: u64 = 60 * 24 * 3600 * 1000;
+/// Lo que se espera a la pestaña
+pub const FETCH_TIMEOUT: Duration = Duration::from_secs(10);
+
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+const PNG_DATA_URL: &str = "data:image/png;base64,";
+/// Un icono de 32×32 no llega ni de lejos
+const MAX_ICON_BYTES: usize = 256 * 1024;
+
+#[derive(Clone, Serialize, Deserialize)]
+struct SiteIcon {
+    /// Nombre del fichero sin ".png"
+    icon: String,
+    fetched: u64,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+struct FaviconIndex {
+    sites: HashMap<String, SiteIcon>,
+}
+
+/// Petición a la espera de FAVICON_SCRIPT, con la pestaña a la que se hizo
+struct Pending {
+    tab_id: String,
+    reply: mpsc::Sender<Option<Vec<u8>>>,
+}
+
+pub struct Favicons {
+    dir: PathBuf,
+    index: FaviconIndex,
+    pending: HashMap<u64, Pending>,
+    next_request: u64,
+}
+
+impl Favicons {
+    /// Olvida lo caducado y borra los ficheros que ya no usa ningún host.
+    pub fn load(profile: PathBuf) -> Self {
+        let dir = profile.join(FAVICONS_DIR);
+        let _ = fs::create_dir_all(&dir);
+        let mut index: FaviconIndex = fs::read_to_string(dir.join(INDEX_FILE))
+            .ok()
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default();
+        let now = now_millis();
+        index
+            .sites
+            .retain(|_, site| now.saturating_sub(site.fetched) < EXPIRE_AFTER_MS);
+
+        let used: HashSet<String> = index
+            .sites
+            .values()
+            .map(|s| format!("{}.png", s.icon))
+            .collect();
+        if let Ok(entries) = fs::read_dir(&dir) {
+            for entry in entries.flatten() {
+                let name = entry.file_name().to_string_lossy().to_string();
+                if name.ends_with(".png") && !used.contains(&name) {
+                    let _ = fs::remove_file(entry.path());
+                }
+            }
+        }
+
+        let favicons = Self {
+            dir,
+            index,
+            pending: HashMap::new(),
+            next_request: 0,
+        };
+        let _ = favicons.save_index();
+        favicons
+    }
+
+    fn save_index(&self) -> Result<(), String> {
+        let json = serde_json::to_string(&self.index).map_err(|e| e.to_string())?;
+        fs::write(self.dir.join(INDEX_FILE), json).map_err(|e| e.to_string())
+    }
+
+    /// Icono guardado para `site` como data URL, y si sigue siendo reciente
+    pub fn get(&self, site: &str) -> Option<(String, bool)> {
+        let entry = self.index.sites.get(site)?;
+        let png = fs::read(self.dir.join(format!("{}.png", entry.icon))).ok()?;
+        let fresh = now_millis().saturating_sub(entry.fetched) < REFRESH_AFTER_MS;
+        Some((to_data_url(&png), fresh))
+    }
+
+    pub fn store(&mut self, site: &str, png: &[u8]) -> Result<(), String> {
+        let icon = format!("{:016x}", fnv1a(png));
+        let path = self.dir.join(format!("{icon}.png"));
+        if !path.exists() {
+            fs::write(&path, png).map_err(|e| e.to_string())?;
+        }
+        self.index.sites.insert(
+            site.to_string(),
+            SiteIcon {
+                icon,
+                fetched: now_millis(),
+            },
+        );
+        self.save_index()
+    }
+
+    /// Nueva petición a `tab_id`: el id va en el script y la respuesta llega
+    /// por el receptor.
+    pub fn request(&mut self, tab_id: &str) -> (u64, mpsc::Receiver<Option<Vec<u8>>>) {
+        self.next_request += 1;
+        let (reply, receiver) = mpsc::channel();
+        self.pending.insert(
+            self.next_request,
+            Pending {
+                tab_id: tab_id.to_string(),
+                reply,
+            },
+        );
+        (self.next_request, receiver)
+    }
+
+    /// Solo cuenta la respuesta de la pestaña a la que se le pidió: otra
+    /// página no puede colar su icono.
+    pub fn resolve(&mut self, request: u64, tab_id: &str, data_url: Option<&str>) {
+        if self
+            .pending
+            .get(&request)
+            .is_some_and(|p| p.tab_id == tab_id)
+        {
+            if let Some(pending) = self.pending.remove(&request) {
+                let _ = pending.reply.send(data_url.and_then(from_data_url));
+            }
+        }
+    }
+
+    pub fn cancel(&mut self, request: u64) {
+        self.pending.remove(&request);
+    }
+}
+
+/// Host de las páginas web; las propias no tienen icono que buscar
+pub fn site_of(url: &str) -> Option<String> {
+    let url = tauri::Url::parse(url).ok()?;
+    if !matches!(url.scheme(), "http" | "https") {
+        return None;
+    }
+    url.host_str().map(str::to_string)
+}
+
+pub fn to_data_url(png: &[u8]) -> String {
+    format!("{PNG_DATA_URL}{}", base64_encode(png))
+}
+
+/// Solo PNG, que es lo que produce FAVICON_SCRIPT. Lo que no cabe en
+/// MAX_ICON_BYTES ni se decodifica.
+fn from_data_url(data_url: &str) -> Option<Vec<u8>> {
+    let encoded = data_url.strip_prefix(PNG_DATA_URL)?;
+    if encoded.len() > MAX_ICON_BYTES.div_ceil(3) * 4 {
+        return None;
+    }
+    let png = base64_decode(encoded)?;
+    (png.starts_with(PNG_SIGNATURE) && png.len() <= MAX_ICON_BYTES).then_some(png)
+}
+
+/// Para nombrar los ficheros: estable entre versiones, a diferencia del
+/// hasher de std
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, &b| {
+        (hash ^ b as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+fn base64_encode(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &b)| n | (b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(BASE64[(n >> (18 - 6 * i)) as usize & 63] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+fn base64_decode(text: &str) -> Option<Vec<u8>> {
+    let text = text.trim_end_matches('=').as_bytes();
+    let mut out = Vec::with_capacity(text.len() * 3 / 4);
+    for chunk in text.chunks(4) {
+        if chunk.len() == 1 {
+            return None;
+        }
+        let mut n = 0u32;
+        for (i, &c) in chunk.iter().enumerate() {
+            let value = BASE64.iter().position(|&b| b == c)? as u32;
+            n |= value << (18 - 6 * i);
+        }
+        for i in 0..chunk.len() - 1 {
+            out.push((n >> (16 - 8 * i)) as u8);
+        }
+    }
+    Some(out)
+}
+
+pub type FaviconState = Arc<Mutex<Favicons>>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn png(len: usize) -> Vec<u8> {
+        let mut png = PNG_SIGNATURE.to_vec();
+        png.extend((0..len.saturating_sub(png.len())).map(|i| i as u8));
+        png
+    }
+
+    #[test]
+    fn base64_round_trip() {
+        let bytes: Vec<u8> = (0..=255).collect();
+        assert_eq!(base64_decode(&base64_encode(&bytes)), Some(bytes));
+        assert_eq!(base64_encode(b""), "");
+        assert_eq!(base64_decode(""), Some(Vec::new()));
+    }
+
+    #[test]
+    fn base64_padding() {
+        assert_eq!(base64_encode(b"abc"), "YWJj");
+        assert_eq!(base64_encode(b"abcd"), "YWJjZA==");
+        assert_eq!(base64_encode(b"abcde"), "YWJjZGU=");
+        assert_eq!(base64_decode("YWJj").as_deref(), Some(&b"abc"[..]));
+        assert_eq!(base64_decode("YWJjZA==").as_deref(), Some(&b"abcd"[..]));
+        assert_eq!(base64_decode("YWJjZGU=").as_deref(), Some(&b"abcde"[..]));
+        // Sin el relleno también vale
+        assert_eq!(base64_decode("YWJjZA").as_deref(), Some(&b"abcd"[..]));
+    }
+
+    #[test]
+    fn base64_rejects_invalid() {
+        assert_eq!(base64_decode("YW*j"), None);
+        assert_eq!(base64_decode("YWJj ZA=="), None);
+        assert_eq!(base64_decode("YW=j"), None);
+        assert_eq!(base64_decode("YWJjZ"), None);
+        assert_eq!(base64_decode("YWJjñ"), None);
+    }
+
+    #[test]
+    fn data_url_round_trip() {
+        let icon = png(100);
+        assert_eq!(from_data_url(&to_data_url(&icon)), Some(icon));
+    }
+
+    #[test]
+    fn data_url_only_png() {
+        let gif = b"GIF89a\x01\x00\x01\x00";
+        assert_eq!(from_data_url(&to_data_url(gif)), None);
+        let other_type = to_data_url(&png(100)).replace("image/png", "image/svg+xml");
+        assert_eq!(from_data_url(&other_type), None);
+        assert_eq!(from_data_url("data:image/png;base64,%%%"), None);
+    }
+
+    #[test]
+    fn data_url_size_limit() {
+        let largest = png(MAX_ICON_BYTES);
+        assert_eq!(from_data_url(&to_data_url(&largest)), Some(largest));
+        assert_eq!(from_data_url(&to_data_url(&png(MAX_ICON_BYTES + 1))), None);
+        let huge = format!("{PNG_DATA_URL}{}", "A".repeat(MAX_ICON_BYTES * 2));
+        assert_eq!(from_data_url(&huge), None);
+    }
+}
@@ -0,0 +1,152 @@
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+// ================================================================
+// HISTORIAL — <perfil>/history.json
+// ================================================================
+// Una entrada por URL con sus visitas y la última, que es lo que usa el
+// buscador rápido para ordenar. Se apunta cada carga terminada de una
+// pestaña que no sea privada. Se escribe cada SAVE_INTERVAL si hubo
+// cambios, y al cerrar.
+// ================================================================
+
+const HISTORY_FILE: &str = "history.json";
+/// Al pasarse de aquí se olvidan las visitadas hace más tiempo
+const MAX_ENTRIES: usize = 5000;
+pub const SAVE_INTERVAL: Duration = Duration::from_secs(30);
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub url: String,
+    #[serde(default)]
+    pub title: String,
+    #[serde(default)]
+    pub visits: u32,
+    /// Milisegundos desde 1970, como Date.now()
+    #[serde(default)]
+    pub last_visit: u64,
+}
+
+pub struct History {
+    path: PathBuf,
+    entries: HashMap<String, HistoryEntry>,
+    dirty: bool,
+}
+
+impl History {
+    pub fn load(profile: PathBuf) -> Self {
+        let path = profile.join(HISTORY_FILE);
+        let entries = fs::read_to_string(&path)
+            .ok()
+            .and_then(|s| serde_json::from_str::<Vec<HistoryEntry>>(&s).ok())
+            .unwrap_or_default()
+            .into_iter()
+            .map(|e| (e.url.clone(), e))
+            .collect();
+        Self {
+            path,
+            entries,
+            dirty: false,
+        }
+    }
+
+    /// Solo páginas web: las propias y about:blank no cuentan
+    pub fn records(url: &str) -> bool {
+        tauri::Url::parse(url).is_ok_and(|u| matches!(u.scheme(), "http" | "https"))
+    }
+
+    pub fn visit(&mut self, url: &str) {
+        if !Self::records(url) {
+            return;
+        }
+        let entry = self
+            .entries
+            .entry(url.to_string())
+            .or_insert_with(|| HistoryEntry {
+                url: url.to_string(),
+                title: String::new(),
+                visits: 0,
+                last_visit: 0,
+            });
+        entry.visits += 1;
+        entry.last_visit = now_millis();
+        self.dirty = true;
+        self.prune();
+    }
+
+    pub fn set_title(&mut self, url: &str, title: &str) {
+        if let Some(entry) = self.entries.get_mut(url).filter(|e| e.title != title) {
+            entry.title = title.to_string();
+            self.dirty = true;
+        }
+    }
+
+    pub fn get(&self, url: &str) -> Option<&HistoryEntry> {
+        self.entries.get(url)
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = &HistoryEntry> {
+        self.entries.values()
+    }
+
+    /// La más reciente primero
+    pub fn list(&self, limit: usize) -> Vec<HistoryEntry> {
+        let mut list: Vec<_> = self.entries.values().cloned().collect();
+        list.sort_by_key(|e| Reverse(e.last_visit));
+        list.truncate(limit);
+        list
+    }
+
+    pub fn remove(&mut self, url: &str) {
+        self.dirty |= self.entries.remove(url).is_some();
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.dirty = true;
+    }
+
+    /// Con algo de margen, para no ordenar en cada visita
+    fn prune(&mut self) {
+        if self.entries.len() <= MAX_ENTRIES + MAX_ENTRIES / 10 {
+            return;
+        }
+        let mut visits: Vec<u64> = self.entries.values().map(|e| e.last_visit).collect();
+        visits.sort_unstable_by_key(|&v| Reverse(v));
+        let oldest_kept = visits[MAX_ENTRIES - 1];
+        self.entries.retain(|_, e| e.last_visit >= oldest_kept);
+    }
+
+    /// Fichero temporal + rename, como la sesión
+    pub fn save_if_dirty(&mut self) {
+        if !self.dirty {
+            return;
+        }
+        let Ok(json) = serde_json::to_string(&self.entries.values().collect::<Vec<_>>()) else {
+            return;
+        };
+        let tmp = self.path.with_extension("json.tmp");
+        let written = fs::File::create(&tmp).and_then(|mut file| {
+            file.write_all(json.as_bytes())?;
+            file.sync_all()
+        });
+        if written.is_ok() && fs::rename(&tmp, &self.path).is_ok() {
+            self.dirty = false;
+        }
+    }
+}
+
+pub fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+pub type HistoryState = Arc<Mutex<History>>;
@@ -0,0 +1,297 @@
+use serde::{Deserialize, Serialize};
+
+use crate::tabs::{BrowserWindow, Split, SplitOrientation};
+
+// ================================================================
+// DISPOSICIÓN — dónde va el webview de cada pestaña
+// ================================================================
+// Todo sale en píxeles lógicos, que es como se pasan a set_bounds y a
+// add_child. El tamaño de la ventana llega en físicos (inner_size, el
+// evento Resized) y aquí se divide por el factor de escala: mezclar
+// unos y otros descolocaba las pestañas en pantallas HiDPI.
+// ================================================================
+
+/// Hueco entre las dos mitades de la vista dividida: ahí la UI dibuja el
+/// divisor que se arrastra
+pub const DIVISOR_GROSOR: f64 = 6.0;
+
+/// Ancho lógico de los paneles que la UI pone a cada lado del contenido
+#[derive(Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Sidebars {
+    pub left: f64,
+    pub right: f64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Rect {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+/// Lo que hace falta saber de la ventana
+pub struct Frame {
+    /// Tamaño interior en píxeles físicos
+    pub width: u32,
+    pub height: u32,
+    pub scale_factor: f64,
+    /// Alto lógico de la barra; en pantalla completa no se cuenta
+    pub toolbar_height: f64,
+    pub fullscreen: bool,
+    pub sidebars: Sidebars,
+}
+
+impl Frame {
+    fn scale(&self) -> f64 {
+        if self.scale_factor > 0.0 {
+            self.scale_factor
+        } else {
+            1.0
+        }
+    }
+
+    /// La ventana entera
+    pub fn window(&self) -> Rect {
+        Rect {
+            x: 0.0,
+            y: 0.0,
+            width: self.width as f64 / self.scale(),
+            height: self.height as f64 / self.scale(),
+        }
+    }
+
+    /// Zona de las pestañas: bajo la barra y entre los paneles laterales
+    pub fn content(&self) -> Rect {
+        let Rect { width, height, .. } = self.window();
+        let top = if self.fullscreen {
+            0.0
+        } else {
+            self.toolbar_height.clamp(0.0, height)
+        };
+        let left = self.sidebars.left.clamp(0.0, width);
+        let right = self.sidebars.right.clamp(0.0, width - left);
+        Rect {
+            x: left,
+            y: top,
+            width: width - left - right,
+            height: height - top,
+        }
+    }
+}
+
+/// Rectángulo de cada pestaña de la ventana. Una página en pantalla
+/// completa ocupa la ventana entera; las de la vista dividida se reparten
+/// la zona de contenido y el resto la ocupa entera, también las ocultas,
+/// para aparecer ya colocadas.
+/// Las que están en su ventana flotante no cuentan.
+pub fn tab_rects(frame: &Frame, window: &BrowserWindow) -> Vec<(String, Rect)> {
+    let content = frame.content();
+    window
+        .tabs
+        .iter()
+        .filter(|tab| !tab.pip)
+        .map(|tab| {
+            let rect = match &window.split {
+                _ if window.page_fullscreen.as_deref() == Some(tab.id.as_str()) => frame.window(),
+                Some(split) if split.contains(&tab.id) => {
+                    split_pane(split, split.panes[0] == tab.id, content, frame.scale())
+                }
+                _ => content,
+            };
+            (tab.id.clone(), rect)
+        })
+        .collect()
+}
+
+/// Mitad de `area` que ocupa cada pestaña de la vista dividida, con
+/// DIVISOR_GROSOR en medio. El corte cae en un píxel físico entero.
+fn split_pane(split: &Split, first: bool, area: Rect, scale: f64) -> Rect {
+    let along = match split.orientation {
+        SplitOrientation::Horizontal => area.width,
+        SplitOrientation::Vertical => area.height,
+    };
+    let space = (along - DIVISOR_GROSOR).max(0.0);
+    let first_len = (space * split.ratio * scale).round() / scale;
+    let (offset, len) = if first {
+        (0.0, first_len)
+    } else {
+        (first_len + DIVISOR_GROSOR, space - first_len)
+    };
+    match split.orientation {
+        SplitOrientation::Horizontal => Rect {
+            x: area.x + offset,
+            width: len,
+            ..area
+        },
+        SplitOrientation::Vertical => Rect {
+            y: area.y + offset,
+            height: len,
+            ..area
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tabs::Tab;
+
+    const TOOLBAR: f64 = 80.0;
+
+    fn frame(width: u32, height: u32, scale_factor: f64) -> Frame {
+        Frame {
+            width,
+            height,
+            scale_factor,
+            toolbar_height: TOOLBAR,
+            fullscreen: false,
+            sidebars: Sidebars::default(),
+        }
+    }
+
+    fn rect(x: f64, y: f64, width: f64, height: f64) -> Rect {
+        Rect {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    fn window_with(ids: &[&str]) -> BrowserWindow {
+        let mut window = BrowserWindow::new("main", false);
+        for id in ids {
+            window.tabs.push(Tab::new(
+                id.to_string(),
+                "https://example.com/".into(),
+                None,
+            ));
+        }
+        window.active_tab = ids.first().map(|id| id.to_string());
+        window
+    }
+
+    fn split(window: &mut BrowserWindow, orientation: SplitOrientation, ratio: f64) {
+        window.split = Some(Split {
+            panes: ["a".into(), "b".into()],
+            orientation,
+            ratio,
+        });
+    }
+
+    fn rect_of(rects: &[(String, Rect)], id: &str) -> Rect {
+        rects.iter().find(|(tab, _)| tab == id).unwrap().1
+    }
+
+    #[test]
+    fn content_is_in_logical_pixels() {
+        assert_eq!(
+            frame(1600, 1000, 1.0).content(),
+            rect(0.0, TOOLBAR, 1600.0, 920.0)
+        );
+        assert_eq!(
+            frame(1600, 1000, 2.0).content(),
+            rect(0.0, TOOLBAR, 800.0, 420.0)
+        );
+        // Sin factor válido se toma 1
+        assert_eq!(
+            frame(1600, 1000, 0.0).window(),
+            rect(0.0, 0.0, 1600.0, 1000.0)
+        );
+    }
+
+    #[test]
+    fn fullscreen_drops_the_toolbar() {
+        let frame = Frame {
+            fullscreen: true,
+            ..frame(1600, 1000, 2.0)
+        };
+        assert_eq!(frame.content(), rect(0.0, 0.0, 800.0, 500.0));
+    }
+
+    #[test]
+    fn oversized_toolbar_and_sidebars_are_clamped() {
+        let frame = Frame {
+            toolbar_height: 5000.0,
+            sidebars: Sidebars {
+                left: 300.0,
+                right: 700.0,
+            },
+            ..frame(800, 600, 1.0)
+        };
+        assert_eq!(frame.content(), rect(300.0, 600.0, 0.0, 0.0));
+
+        let frame = Frame {
+            sidebars: Sidebars {
+                left: 1000.0,
+                right: 1000.0,
+            },
+            ..frame
+        };
+        let content = frame.content();
+        assert_eq!(content.x, 800.0);
+        assert_eq!(content.width, 0.0);
+    }
+
+    #[test]
+    fn split_ratio_extremes() {
+        let frame = frame(1006, 600, 1.0);
+        let mut window = window_with(&["a", "b"]);
+
+        split(&mut window, SplitOrientation::Horizontal, 0.0);
+        let rects = tab_rects(&frame, &window);
+        assert_eq!(rect_of(&rects, "a"), rect(0.0, TOOLBAR, 0.0, 520.0));
+        assert_eq!(
+            rect_of(&rects, "b"),
+            rect(DIVISOR_GROSOR, TOOLBAR, 1000.0, 520.0)
+        );
+
+        split(&mut window, SplitOrientation::Horizontal, 1.0);
+        let rects = tab_rects(&frame, &window);
+        assert_eq!(rect_of(&rects, "a"), rect(0.0, TOOLBAR, 1000.0, 520.0));
+        assert_eq!(rect_of(&rects, "b"), rect(1006.0, TOOLBAR, 0.0, 520.0));
+    }
+
+    #[test]
+    fn split_cut_falls_on_a_physical_pixel() {
+        let frame = frame(1001, 800, 1.5);
+        let mut window = window_with(&["a", "b"]);
+        split(&mut window, SplitOrientation::Vertical, 0.333);
+        let rects = tab_rects(&frame, &window);
+        let (a, b) = (rect_of(&rects, "a"), rect_of(&rects, "b"));
+        let physical = a.height * 1.5;
+        assert!((physical - physical.round()).abs() < 1e-9);
+        assert!((a.height + DIVISOR_GROSOR + b.height - frame.content().height).abs() < 1e-9);
+    }
+
+    #[test]
+    fn divisor_wider_than_the_content() {
+        let frame = frame(4, 600, 1.0);
+        let mut window = window_with(&["a", "b"]);
+        split(&mut window, SplitOrientation::Horizontal, 0.5);
+        let rects = tab_rects(&frame, &window);
+        assert_eq!(rect_of(&rects, "a").width, 0.0);
+        assert_eq!(rect_of(&rects, "b").width, 0.0);
+    }
+
+    #[test]
+    fn page_fullscreen_takes_the_whole_window() {
+        let frame = frame(1600, 1000, 2.0);
+        let mut window = window_with(&["a", "b", "c"]);
+        split(&mut window, SplitOrientation::Horizontal, 0.5);
+        window.page_fullscreen = Some("a".into());
+        let rects = tab_rects(&frame, &window);
+        assert_eq!(rect_of(&rects, "a"), frame.window());
+        assert_eq!(rect_of(&rects, "c"), frame.content());
+    }
+
+    #[test]
+    fn pip_tabs_are_left_out() {
+        let mut window = window_with(&["a", "b"]);
+        window.tabs[1].pip = true;
+        let rects = tab_rects(&frame(800, 600, 1.0), &window);
+        assert_eq!(rects.len(), 1);
+        assert_eq!(rects[0].0, "a");
+    }
+}
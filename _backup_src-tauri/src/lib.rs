@@ -1,4 +1,4 @@
-use serde::Serialize;
+#[cfg(any(target_os = "windows", target_os = "linux"))]
 use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
 use tauri::{Emitter, Manager, WebviewUrl};
@@ -7,472 +7,3590 @@ use tauri::{Emitter, Manager, WebviewUrl};
 const BARRA_ALTURA: f64 = 72.0;
 
 mod ad_blocker;
+mod bookmarks;
+mod containers;
+mod diagnostics;
+mod discard;
+mod events;
+mod favicons;
+mod history;
+mod layout;
+mod popups;
+mod private;
+mod session;
+mod settings;
+mod shield;
+mod switcher;
+mod tabs;
+mod user_scripts;
+mod user_styles;
+mod watcher;
 
-// --- SCRIPTS DE SEGURIDAD (MODULARES) ---
-// Each module is a self-contained IIFE that only runs where needed.
-// Order matters: m0_core must be first (defines __atomShield).
-const SHIELD_SCRIPTS: &[&str] = &[
-    include_str!("../scripts/m0_core.js"),           // Utilities (always)
-    include_str!("../scripts/m1_youtube.js"),         // YT ad stripping (YT only)
-    include_str!("../scripts/m2_youtube_cosmetics.js"), // YT cosmetics (YT only)
-    include_str!("../scripts/m3_gpt_mock.js"),        // GPT mock (non-YT)
-    include_str!("../scripts/m4_ad_mocks.js"),        // IMA/Amazon/etc (non-YT)
-    include_str!("../scripts/m5_anti_detection.js"),  // Anti-adblock (non-YT)
-    include_str!("../scripts/m6_network_intercept.js"), // Fetch/XHR (non-YT)
-    include_str!("../scripts/m7_cosmetics.js"),       // CSS rules (non-YT)
-];
-
-// --- ESTADO ---
-struct TabManager {
-    tabs: HashMap<String, String>,
-    active_tab: Option<String>,
-    counter: u32,
-    is_fullscreen: bool,
-    pre_fs_pos: Option<(i32, i32)>,
-    pre_fs_size: Option<(u32, u32)>,
+use bookmarks::{Bookmark, BookmarkState, Bookmarks};
+use containers::{Container, ContainerColor, ContainerState, Containers, HostRule};
+use diagnostics::{DiagnosticsState, ShieldDiagnostics, ShieldReport, TabDiagnostics};
+use discard::{DiscardPolicy, DiscardState, Discarder};
+use events::TabEvent;
+use favicons::{FaviconState, Favicons};
+use history::{History, HistoryEntry, HistoryState};
+use layout::{Frame, Rect, Sidebars};
+use popups::{PopupBlocker, PopupDecision, PopupState};
+use session::{
+    ClosedTab, Session, SessionState, SessionStore, SessionTab, SessionWindow, WindowGeometry,
+};
+use settings::{Settings, SettingsState, SettingsStore};
+use shield::{Phase, RunAt, World};
+use switcher::{Candidate, SwitchResult, Target};
+use tabs::{BrowserWindow, SplitOrientation, Tab, TabManager, TabState, MAIN_WINDOW};
+use user_scripts::{UserScriptInfo, UserScriptState, UserScripts};
+use user_styles::{UserStyleInfo, UserStyleState, UserStyles};
+
+// ================================================================
+// INYECCIÓN TEMPRANA DEL SCRIPT — WebView2 nativo
+// ================================================================
+// AddScriptToExecuteOnDocumentCreated ejecuta el script ANTES de que
+// cualquier script de la página se ejecute. Se aplica a todas las
+// navegaciones futuras + iframes del main frame, así que se vuelve a
+// registrar en cada navegación con los módulos del host de destino
+// (ver shield.rs) y se retira el anterior.
+//
+// IMPORTANTE: No se inyecta en iframes sandboxed (about:blank sin
+// allow-scripts) — eso causaba los errores anteriores. WebView2
+// maneja esto automáticamente: solo inyecta donde está permitido.
+// ================================================================
+
+/// Todo lo que va a inicio de documento en `url` dentro de `worlds`:
+/// shield, user scripts (mundo de la página, como @grant none) y user
+/// styles (solo tocan el DOM). Cada parte es un script independiente, así
+/// que un user script que no compila no impide que se ejecute el resto.
+fn early_scripts_for(app: &tauri::AppHandle, url: &str, worlds: &[World]) -> Vec<String> {
+    let modules = shield::modules_for(url);
+    let mut scripts = vec![shield::script_for(
+        &modules,
+        RunAt::DocumentStart,
+        worlds,
+        Phase::Early,
+    )];
+    if worlds.contains(&World::Page) {
+        if let Some(user_scripts) = app.try_state::<UserScriptState>() {
+            if let Ok(user_scripts) = user_scripts.lock() {
+                scripts.extend(user_scripts.scripts_for(url, RunAt::DocumentStart));
+            }
+        }
+    }
+    if worlds.contains(&World::Isolated) {
+        if let Some(user_styles) = app.try_state::<UserStyleState>() {
+            if let Ok(user_styles) = user_styles.lock() {
+                scripts.push(user_styles.script_for(url));
+            }
+        }
+    }
+    scripts.retain(|s| !s.is_empty());
+    scripts
+}
+
+#[cfg(target_os = "windows")]
+struct EarlyScript {
+    key: u64,
+    script_ids: Vec<String>,
+}
+
+/// Scripts registrados actualmente en cada pestaña (por label).
+#[cfg(target_os = "windows")]
+fn early_scripts() -> &'static Mutex<HashMap<String, EarlyScript>> {
+    static SCRIPTS: std::sync::OnceLock<Mutex<HashMap<String, EarlyScript>>> =
+        std::sync::OnceLock::new();
+    SCRIPTS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+#[cfg(target_os = "windows")]
+fn inject_shield_early(webview: &tauri::Webview, url: &str) {
+    use std::hash::{Hash, Hasher};
+    use windows::core::HSTRING;
+
+    let parts = early_scripts_for(webview.app_handle(), url, shield::ALL_WORLDS);
+
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    parts.hash(&mut hasher);
+    let key = hasher.finish();
+
+    let label = webview.label().to_string();
+    let previous_ids = {
+        let mut scripts = match early_scripts().lock() {
+            Ok(s) => s,
+            Err(_) => return,
+        };
+        if scripts.get(&label).is_some_and(|s| s.key == key) {
+            // Mismo conjunto de módulos: los scripts registrados siguen valiendo
+            return;
+        }
+        scripts
+            .insert(
+                label.clone(),
+                EarlyScript {
+                    key,
+                    script_ids: Vec::new(),
+                },
+            )
+            .map(|old| old.script_ids)
+            .unwrap_or_default()
+    };
+
+    let _ = webview.with_webview(move |wv| {
+        unsafe {
+            let controller = wv.controller();
+            let core = controller.CoreWebView2().unwrap();
+
+            for old_id in previous_ids {
+                let _ = core.RemoveScriptToExecuteOnDocumentCreated(&HSTRING::from(old_id));
+            }
+
+            for part in parts {
+                let hscript = HSTRING::from(part);
+                let core_ = core.clone();
+                let label = label.clone();
+                let handler =
+                    webview2_com::AddScriptToExecuteOnDocumentCreatedCompletedHandler::create(
+                        Box::new(move |hr, id| {
+                            if hr.is_err() {
+                                return Ok(());
+                            }
+                            let mut scripts = match early_scripts().lock() {
+                                Ok(s) => s,
+                                Err(_) => return Ok(()),
+                            };
+                            match scripts.get_mut(&label) {
+                                // Sigue siendo el conjunto vigente: guardar su id para retirarlo luego
+                                Some(entry) if entry.key == key => entry.script_ids.push(id),
+                                // Otra navegación lo reemplazó mientras se registraba
+                                _ => {
+                                    let _ = core_
+                                        .RemoveScriptToExecuteOnDocumentCreated(&HSTRING::from(id));
+                                }
+                            }
+                            Ok(())
+                        }),
+                    );
+                let _ = core.AddScriptToExecuteOnDocumentCreated(&hscript, &handler);
+            }
+        }
+    });
+}
+
+#[cfg(target_os = "windows")]
+fn forget_shield_early(_app: &tauri::AppHandle, tab_id: &str) {
+    if let Ok(mut scripts) = early_scripts().lock() {
+        scripts.remove(tab_id);
+    }
+}
+
+// ================================================================
+// INYECCIÓN TEMPRANA EN LINUX — WebKitGTK UserContentManager
+// ================================================================
+// Equivalente a AddScriptToExecuteOnDocumentCreated: un WebKitUserScript
+// por cada parte de early_scripts_for, a inicio de documento y en todos
+// los frames. Como en WebView2, se sustituye en cada navegación por los
+// módulos del host de destino. Los módulos World::Isolated van a un
+// mundo aislado propio.
+//
+// La sustitución se hace en load-changed (Started/Redirected), que solo
+// salta para el documento principal, y de forma síncrona: on_navigation
+// es decide-policy, que también salta para los iframes, y un with_webview
+// desde ahí llegaría tarde.
+//
+// Los objetos de WebKit no son Send: el registro vive en el hilo
+// principal (donde se ejecuta with_webview), en un thread_local.
+// ================================================================
+
+#[cfg(target_os = "linux")]
+const SHIELD_WORLD: &str = "atom-shield";
+
+#[cfg(target_os = "linux")]
+struct EarlyScript {
+    key: u64,
+    scripts: Vec<webkit2gtk::UserScript>,
+}
+
+#[cfg(target_os = "linux")]
+thread_local! {
+    /// Scripts registrados actualmente en cada pestaña (por label).
+    static EARLY_SCRIPTS: std::cell::RefCell<HashMap<String, EarlyScript>> =
+        std::cell::RefCell::new(HashMap::new());
+}
+
+/// Registra en `view` los scripts de inicio de documento de `url`. Hilo
+/// principal.
+#[cfg(target_os = "linux")]
+fn register_shield_early(
+    app: &tauri::AppHandle,
+    view: &webkit2gtk::WebView,
+    label: &str,
+    url: &str,
+) {
+    use std::hash::{Hash, Hasher};
+    use webkit2gtk::{
+        UserContentInjectedFrames, UserContentManagerExt, UserScript, UserScriptInjectionTime,
+        WebViewExt,
+    };
+
+    let Some(manager) = view.user_content_manager() else {
+        return;
+    };
+    let page = early_scripts_for(app, url, &[World::Page]);
+    let isolated = early_scripts_for(app, url, &[World::Isolated]);
+
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    (&page, &isolated).hash(&mut hasher);
+    let key = hasher.finish();
+
+    EARLY_SCRIPTS.with(|registered| {
+        let mut registered = registered.borrow_mut();
+        if registered.get(label).is_some_and(|s| s.key == key) {
+            // Mismo conjunto de módulos: los scripts registrados siguen valiendo
+            return;
+        }
+        // Solo los nuestros: remove_all_scripts se llevaría también los de Tauri
+        if let Some(old) = registered.remove(label) {
+            for script in &old.scripts {
+                manager.remove_script(script);
+            }
+        }
+
+        let mut scripts: Vec<UserScript> = page
+            .iter()
+            .map(|source| {
+                UserScript::new(
+                    source,
+                    UserContentInjectedFrames::AllFrames,
+                    UserScriptInjectionTime::Start,
+                    &[],
+                    &[],
+                )
+            })
+            .collect();
+        scripts.extend(isolated.iter().map(|source| {
+            UserScript::for_world(
+                source,
+                UserContentInjectedFrames::AllFrames,
+                UserScriptInjectionTime::Start,
+                SHIELD_WORLD,
+                &[],
+                &[],
+            )
+        }));
+        for script in &scripts {
+            manager.add_script(script);
+        }
+        registered.insert(label.to_string(), EarlyScript { key, scripts });
+    });
+}
+
+/// Fuera de una navegación (primera carga, user scripts cambiados)
+#[cfg(target_os = "linux")]
+fn inject_shield_early(webview: &tauri::Webview, url: &str) {
+    let app = webview.app_handle().clone();
+    let (label, url) = (webview.label().to_string(), url.to_string());
+    let _ = webview.with_webview(move |wv| {
+        register_shield_early(&app, &wv.inner(), &label, &url);
+    });
+}
+
+#[cfg(target_os = "linux")]
+fn forget_shield_early(app: &tauri::AppHandle, tab_id: &str) {
+    let label = tab_id.to_string();
+    let _ = app.run_on_main_thread(move || {
+        EARLY_SCRIPTS.with(|registered| {
+            registered.borrow_mut().remove(&label);
+        });
+    });
 }
 
-impl TabManager {
-    fn new() -> Self {
-        Self {
-            tabs: HashMap::new(),
-            active_tab: None,
-            counter: 0,
-            is_fullscreen: false,
-            pre_fs_pos: None,
-            pre_fs_size: None,
+/// Vuelve a registrar la inyección temprana en todas las pestañas
+/// abiertas, p. ej. tras activar o recargar user scripts.
+#[cfg(any(target_os = "windows", target_os = "linux"))]
+fn refresh_shield_early(app: &tauri::AppHandle) {
+    let tab_ids: Vec<String> = match app.state::<TabState>().lock() {
+        Ok(manager) => manager.ids(),
+        Err(_) => return,
+    };
+    for id in tab_ids {
+        if let Some(webview) = app.get_webview(&id) {
+            if let Ok(url) = webview.url() {
+                inject_shield_early(&webview, url.as_str());
+            }
         }
     }
+}
 
-    fn new_id(&mut self) -> String {
-        self.counter += 1;
-        format!("tab-{}", self.counter)
+#[cfg(not(any(target_os = "windows", target_os = "linux")))]
+fn inject_shield_early(webview: &tauri::Webview, url: &str) {
+    for script in early_scripts_for(webview.app_handle(), url, shield::ALL_WORLDS) {
+        let _ = webview.eval(script);
     }
 }
 
-type TabState = Arc<Mutex<TabManager>>;
+#[cfg(not(any(target_os = "windows", target_os = "linux")))]
+fn forget_shield_early(_app: &tauri::AppHandle, _tab_id: &str) {}
 
-#[derive(Clone, Serialize)]
-struct TabInfo {
-    id: String,
-    url: String,
+/// Sin registro persistente que actualizar: la siguiente carga ya
+/// recoge los cambios vía inject_shield_fallback.
+#[cfg(not(any(target_os = "windows", target_os = "linux")))]
+fn refresh_shield_early(_app: &tauri::AppHandle) {}
+
+// ================================================================
+// INYECCIÓN DE RESPALDO — on_page_load
+// ================================================================
+// Red de seguridad para SPA navigations y recargas, y momento de
+// ejecución de los módulos DocumentIdle.
+// Los módulos usan IIFEs, así que la doble ejecución es segura.
+// ================================================================
+
+fn inject_shield_fallback(webview: &tauri::Webview, url: &str) {
+    let modules = shield::modules_for(url);
+    let user_scripts = webview.app_handle().try_state::<UserScriptState>();
+    let user_scripts = user_scripts.as_ref().and_then(|u| u.lock().ok());
+
+    // Cada user script en su propio eval: uno roto no arrastra al shield
+    for run_at in [RunAt::DocumentStart, RunAt::DocumentIdle] {
+        let mut scripts = vec![shield::script_for(
+            &modules,
+            run_at,
+            shield::ALL_WORLDS,
+            Phase::Fallback,
+        )];
+        if let Some(ref user_scripts) = user_scripts {
+            scripts.extend(user_scripts.scripts_for(url, run_at));
+        }
+        for script in scripts.into_iter().filter(|s| !s.is_empty()) {
+            let _ = webview.eval(script);
+        }
+    }
+
+    // Los <style> se sustituyen por id, así que repetir es inocuo
+    if let Some(user_styles) = webview.app_handle().try_state::<UserStyleState>() {
+        if let Ok(user_styles) = user_styles.lock() {
+            let script = user_styles.script_for(url);
+            if !script.is_empty() {
+                let _ = webview.eval(script);
+            }
+        }
+    }
+}
+
+/// Tras cambiar algún fichero de userstyles: actualiza los <style> de
+/// todas las pestañas abiertas y la inyección de las próximas cargas.
+fn refresh_user_styles(app: &tauri::AppHandle) {
+    let user_styles = app.state::<UserStyleState>().inner().clone();
+    if let Ok(mut user_styles) = user_styles.lock() {
+        user_styles.reload();
+    }
+
+    let tab_ids: Vec<String> = match app.state::<TabState>().lock() {
+        Ok(manager) => manager.ids(),
+        Err(_) => return,
+    };
+    for id in tab_ids {
+        let Some(webview) = app.get_webview(&id) else {
+            continue;
+        };
+        let Ok(url) = webview.url() else {
+            continue;
+        };
+        let script = match user_styles.lock() {
+            Ok(user_styles) => user_styles.update_script_for(url.as_str()),
+            Err(_) => return,
+        };
+        let _ = webview.eval(script);
+    }
+
+    refresh_shield_early(app);
 }
 
 // ================================================================
-// INYECCIÓN TEMPRANA DEL SCRIPT — WebView2 nativo
+// ESTADO DE CADA PESTAÑA — título, favicon, carga, historial, audio
 // ================================================================
-// AddScriptToExecuteOnDocumentCreated ejecuta el script ANTES de que
-// cualquier script de la página se ejecute. Se registra UNA VEZ y
-// se aplica a todas las navegaciones futuras + iframes del main frame.
-//
-// IMPORTANTE: No se inyecta en iframes sandboxed (about:blank sin
-// allow-scripts) — eso causaba los errores anteriores. WebView2
-// maneja esto automáticamente: solo inyecta donde está permitido.
+// Título, carga y URL llegan por los eventos de Tauri. Atrás/adelante,
+// favicon declarado y audio solo los da cada motor, así que se
+// enganchan de forma nativa como las ventanas nuevas.
 // ================================================================
 
+/// Aplica `change` a la pestaña y emite "tab-updated" a su ventana si
+/// algo cambió.
+fn update_tab(app: &tauri::AppHandle, tab_id: &str, change: impl FnOnce(&mut Tab)) {
+    let state = app.state::<TabState>();
+    let updated = {
+        let Ok(mut manager) = state.lock() else {
+            return;
+        };
+        let Some(window) = manager.window_of_mut(tab_id) else {
+            return;
+        };
+        let label = window.label.clone();
+        let Some(tab) = window.tabs.iter_mut().find(|t| t.id == tab_id) else {
+            return;
+        };
+        let before = tab.clone();
+        change(tab);
+        (*tab != before).then(|| (label, tab.clone()))
+    };
+    if let Some((label, tab)) = updated {
+        let _ = app.emit_to(label.as_str(), "tab-updated", tab);
+        save_session(app);
+    }
+}
+
+fn emit_tab_event(app: &tauri::AppHandle, event: TabEvent) {
+    for label in event.windows() {
+        let _ = app.emit_to(label, event.name(), &event);
+    }
+}
+
+/// Ventana, posición y estado de la pestaña
+fn tab_snapshot(app: &tauri::AppHandle, tab_id: &str) -> Option<(String, usize, Tab)> {
+    let state = app.state::<TabState>();
+    let manager = state.lock().ok()?;
+    let window = manager.window_of(tab_id)?;
+    let index = window.index_of(tab_id)?;
+    Some((window.label.clone(), index, window.tabs[index].clone()))
+}
+
+/// "tab-created" para una pestaña recién añadida al modelo, antes de
+/// cargarla: los "tab-activated" y "tab-updated" de la carga llegan después.
+fn tab_created(app: &tauri::AppHandle, tab_id: &str, active: bool) {
+    if let Some((window, index, tab)) = tab_snapshot(app, tab_id) {
+        emit_tab_event(
+            app,
+            TabEvent::Created {
+                tab,
+                window,
+                index,
+                active,
+            },
+        );
+    }
+}
+
+fn tab_activated(app: &tauri::AppHandle, tab_id: &str, previous: Option<String>) {
+    if let Some((window, _, tab)) = tab_snapshot(app, tab_id) {
+        emit_tab_event(
+            app,
+            TabEvent::Activated {
+                tab,
+                window,
+                previous,
+            },
+        );
+    }
+}
+
+fn tab_moved(app: &tauri::AppHandle, tab_id: &str, from: String) {
+    if let Some((to, index, tab)) = tab_snapshot(app, tab_id) {
+        emit_tab_event(
+            app,
+            TabEvent::Moved {
+                tab,
+                from,
+                to,
+                index,
+            },
+        );
+    }
+}
+
 #[cfg(target_os = "windows")]
-fn inject_shield_early(webview: &tauri::Webview) {
-    use windows::core::HSTRING;
+fn setup_tab_state_watchers(webview: &tauri::Webview, tab_id: String) {
+    use webview2_com::Microsoft::Web::WebView2::Win32::{ICoreWebView2_15, ICoreWebView2_8};
+    use webview2_com::{
+        take_pwstr, ContainsFullScreenElementChangedEventHandler, FaviconChangedEventHandler,
+        HistoryChangedEventHandler, IsDocumentPlayingAudioChangedEventHandler,
+        IsMutedChangedEventHandler,
+    };
+    use windows::core::{Interface, BOOL, PWSTR};
+
+    fn audio_state(core: &ICoreWebView2_8) -> (bool, bool) {
+        let (mut audible, mut muted): (BOOL, BOOL) = (false.into(), false.into());
+        unsafe {
+            let _ = core.IsDocumentPlayingAudio(&mut audible);
+            let _ = core.IsMuted(&mut muted);
+        }
+        (audible.as_bool(), muted.as_bool())
+    }
+
+    let app = webview.app_handle().clone();
+    let _ = webview.with_webview(move |wv| {
+        unsafe {
+            let controller = wv.controller();
+            let core = controller.CoreWebView2().unwrap();
+            let mut token: i64 = 0;
+
+            // Atrás/adelante y URL, también tras pushState
+            let (history_app, history_id) = (app.clone(), tab_id.clone());
+            let _ = core.add_HistoryChanged(
+                &HistoryChangedEventHandler::create(Box::new(move |sender, _| {
+                    if let Some(core) = sender {
+                        let (mut back, mut forward): (BOOL, BOOL) = (false.into(), false.into());
+                        let _ = core.CanGoBack(&mut back);
+                        let _ = core.CanGoForward(&mut forward);
+                        let mut source = PWSTR::null();
+                        let url = core.Source(&mut source).ok().map(|_| take_pwstr(source));
+                        update_tab(&history_app, &history_id, |tab| {
+                            tab.can_go_back = back.as_bool();
+                            tab.can_go_forward = forward.as_bool();
+                            if let Some(url) = url {
+                                tab.set_url(&url);
+                            }
+                        });
+                    }
+                    Ok(())
+                })),
+                &mut token,
+            );
+
+            // Pantalla completa pedida por la página: WebView2 no cambia
+            // nada por su cuenta, solo avisa
+            let (fullscreen_app, fullscreen_id) = (app.clone(), tab_id.clone());
+            let _ = core.add_ContainsFullScreenElementChanged(
+                &ContainsFullScreenElementChangedEventHandler::create(Box::new(
+                    move |sender, _| {
+                        if let Some(core) = sender {
+                            let mut full: BOOL = false.into();
+                            let _ = core.ContainsFullScreenElement(&mut full);
+                            page_fullscreen_changed(
+                                &fullscreen_app,
+                                &fullscreen_id,
+                                full.as_bool(),
+                            );
+                        }
+                        Ok(())
+                    },
+                )),
+                &mut token,
+            );
+
+            // Favicon declarado por la página (runtime 1.0.1185+)
+            if let Ok(core15) = core.cast::<ICoreWebView2_15>() {
+                let (favicon_app, favicon_id) = (app.clone(), tab_id.clone());
+                let _ = core15.add_FaviconChanged(
+                    &FaviconChangedEventHandler::create(Box::new(move |sender, _| {
+                        if let Some(core15) = sender.and_then(|s| s.cast::<ICoreWebView2_15>().ok())
+                        {
+                            let mut uri_ptr = PWSTR::null();
+                            if core15.FaviconUri(&mut uri_ptr).is_ok() {
+                                let uri = take_pwstr(uri_ptr);
+                                update_tab(&favicon_app, &favicon_id, |tab| {
+                                    tab.favicon = Some(uri).filter(|u| !u.is_empty());
+                                });
+                            }
+                        }
+                        Ok(())
+                    })),
+                    &mut token,
+                );
+            }
+
+            // Sonido y silencio (runtime 1.0.864+)
+            if let Ok(core8) = core.cast::<ICoreWebView2_8>() {
+                let (audio_app, audio_id) = (app.clone(), tab_id.clone());
+                let _ = core8.add_IsDocumentPlayingAudioChanged(
+                    &IsDocumentPlayingAudioChangedEventHandler::create(Box::new(
+                        move |sender, _| {
+                            if let Some(core8) =
+                                sender.and_then(|s| s.cast::<ICoreWebView2_8>().ok())
+                            {
+                                let (audible, muted) = audio_state(&core8);
+                                update_tab(&audio_app, &audio_id, |tab| {
+                                    tab.audible = audible;
+                                    tab.muted = muted;
+                                });
+                            }
+                            Ok(())
+                        },
+                    )),
+                    &mut token,
+                );
+                let (muted_app, muted_id) = (app.clone(), tab_id.clone());
+                let _ = core8.add_IsMutedChanged(
+                    &IsMutedChangedEventHandler::create(Box::new(move |sender, _| {
+                        if let Some(core8) = sender.and_then(|s| s.cast::<ICoreWebView2_8>().ok()) {
+                            let (audible, muted) = audio_state(&core8);
+                            update_tab(&muted_app, &muted_id, |tab| {
+                                tab.audible = audible;
+                                tab.muted = muted;
+                            });
+                        }
+                        Ok(())
+                    })),
+                    &mut token,
+                );
+            }
+        }
+    });
+}
+
+#[cfg(target_os = "linux")]
+fn setup_tab_state_watchers(webview: &tauri::Webview, tab_id: String) {
+    use webkit2gtk::{
+        BackForwardListExt, BackForwardListItemExt, FaviconDatabaseExt, LoadEvent, WebContextExt,
+        WebViewExt,
+    };
+
+    fn sync_history(app: &tauri::AppHandle, tab_id: &str, view: &webkit2gtk::WebView) {
+        let url = view.uri().map(|u| u.to_string());
+        let (back, forward) = (view.can_go_back(), view.can_go_forward());
+        update_tab(app, tab_id, |tab| {
+            tab.can_go_back = back;
+            tab.can_go_forward = forward;
+            if let Some(url) = url {
+                tab.set_url(&url);
+            }
+        });
+    }
+
+    let app = webview.app_handle().clone();
+    let _ = webview.with_webview(move |wv| {
+        let view = wv.inner();
+
+        // Atrás/adelante y URL, también tras pushState
+        let (uri_app, uri_id) = (app.clone(), tab_id.clone());
+        view.connect_uri_notify(move |view| sync_history(&uri_app, &uri_id, view));
+        let (load_app, load_id) = (app.clone(), tab_id.clone());
+        view.connect_load_changed(move |view, _| sync_history(&load_app, &load_id, view));
+
+        // Navegación del documento principal: se abre en otra pestaña si
+        // no le toca quedarse en esta y, si no, los módulos del host de
+        // destino. La entrada actual de la lista atrás/adelante sigue
+        // siendo la página de la que sale hasta que se confirma la carga;
+        // sin ella es la carga inicial, que se queda donde se abrió
+        let (main_app, main_id) = (app.clone(), tab_id.clone());
+        view.connect_load_changed(move |view, event| {
+            if !matches!(event, LoadEvent::Started | LoadEvent::Redirected) {
+                return;
+            }
+            let Some(uri) = view.uri() else {
+                return;
+            };
+            if let Ok(url) = tauri::Url::parse(&uri) {
+                let current = view
+                    .back_forward_list()
+                    .and_then(|l| l.current_item())
+                    .and_then(|item| item.uri());
+                if current.is_some()
+                    && opens_elsewhere(&main_app, &main_id, current.as_deref(), &url)
+                {
+                    view.stop_loading();
+                    open_elsewhere(&main_app, &main_id, &url);
+                    return;
+                }
+            }
+            register_shield_early(&main_app, view, &main_id, &uri);
+        });
+
+        // Favicon declarado: solo si el contexto tiene base de datos de favicons
+        let (favicon_app, favicon_id) = (app.clone(), tab_id.clone());
+        view.connect_favicon_notify(move |view| {
+            let uri = view
+                .uri()
+                .and_then(|page| view.context()?.favicon_database()?.favicon_uri(&page));
+            if let Some(uri) = uri {
+                update_tab(&favicon_app, &favicon_id, |tab| {
+                    tab.favicon = Some(uri.to_string());
+                });
+            }
+        });
+
+        let (audio_app, audio_id) = (app.clone(), tab_id.clone());
+        view.connect_is_playing_audio_notify(move |view| {
+            let audible = view.is_playing_audio();
+            update_tab(&audio_app, &audio_id, |tab| tab.audible = audible);
+        });
+        let (muted_app, muted_id) = (app.clone(), tab_id.clone());
+        view.connect_is_muted_notify(move |view| {
+            let muted = view.is_muted();
+            update_tab(&muted_app, &muted_id, |tab| tab.muted = muted);
+        });
+
+        // Pantalla completa pedida por la página. true: WebKit no pone él
+        // la ventana entera en pantalla completa, se hace aquí
+        let (enter_app, enter_id) = (app.clone(), tab_id.clone());
+        view.connect_enter_fullscreen(move |_| {
+            page_fullscreen_changed(&enter_app, &enter_id, true);
+            true
+        });
+        let (leave_app, leave_id) = (app.clone(), tab_id.clone());
+        view.connect_leave_fullscreen(move |_| {
+            page_fullscreen_changed(&leave_app, &leave_id, false);
+            true
+        });
+    });
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "linux")))]
+fn setup_tab_state_watchers(_webview: &tauri::Webview, _tab_id: String) {}
+
+// ================================================================
+// PANTALLA COMPLETA — la de la ventana (F11) y la de cada página
+// ================================================================
+// Las dos usan la pantalla completa real de la ventana. La de una
+// página (el botón de un vídeo) es solo de esa pestaña: ocupa toda la
+// ventana sin barra y termina al salir con Esc o exitFullscreen, al
+// cambiar de pestaña o al cerrarla. La geometría de antes la devuelve
+// el sistema al salir.
+// ================================================================
+
+const EXIT_FULLSCREEN_SCRIPT: &str = "if (document.fullscreenElement) document.exitFullscreen();";
+
+/// La ventana en pantalla completa mientras lo pida ella o una de sus
+/// páginas, con los webviews colocados para ello.
+fn apply_fullscreen(app: &tauri::AppHandle, label: &str) {
+    let Some(win) = app.get_window(label) else {
+        return;
+    };
+    let current = win.is_fullscreen().unwrap_or(false);
+    let geometry = match (win.outer_position(), win.inner_size()) {
+        (Ok(pos), Ok(size)) if !current => Some(((pos.x, pos.y), (size.width, size.height))),
+        _ => None,
+    };
+
+    let (wanted, page, visible) = {
+        let state = app.state::<TabState>();
+        let Ok(mut manager) = state.lock() else {
+            return;
+        };
+        let Some(window) = manager.window_mut(label) else {
+            return;
+        };
+        let wanted = window.is_fullscreen || window.page_fullscreen.is_some();
+        // Para la sesión: la geometría que vale es la de antes
+        if wanted && !current {
+            if let Some((pos, size)) = geometry {
+                window.pre_fs_pos = Some(pos);
+                window.pre_fs_size = Some(size);
+            }
+        } else if !wanted {
+            window.pre_fs_pos = None;
+            window.pre_fs_size = None;
+        }
+        (wanted, window.page_fullscreen.clone(), window.visible())
+    };
 
-    // Concatenar todos los módulos en un solo string
-    let mut combined = String::new();
-    for script in SHIELD_SCRIPTS {
-        combined.push_str(script);
-        combined.push('\n');
+    if wanted != current {
+        let _ = win.set_fullscreen(wanted);
     }
+    // Con una página en pantalla completa la otra mitad de la vista
+    // dividida queda detrás
+    for id in visible {
+        if let Some(view) = app.get_webview(&id) {
+            if page.as_ref().is_some_and(|p| *p != id) {
+                let _ = view.hide();
+            } else {
+                let _ = view.show();
+            }
+        }
+    }
+    layout_tabs(&win);
+}
+
+/// La página de `tab_id` entra o sale de pantalla completa
+fn page_fullscreen_changed(app: &tauri::AppHandle, tab_id: &str, fullscreen: bool) {
+    let label = {
+        let state = app.state::<TabState>();
+        let Ok(mut manager) = state.lock() else {
+            return;
+        };
+        let Some(window) = manager.window_of_mut(tab_id) else {
+            return;
+        };
+        if fullscreen && window.visible().iter().any(|id| id == tab_id) {
+            window.page_fullscreen = Some(tab_id.to_string());
+        } else if window.page_fullscreen.as_deref() == Some(tab_id) {
+            window.page_fullscreen = None;
+        }
+        window.label.clone()
+    };
+    apply_fullscreen(app, &label);
+    let _ = app.emit_to(
+        label.as_str(),
+        "page-fullscreen-change",
+        serde_json::json!({
+            "id": tab_id,
+            "fullscreen": fullscreen
+        }),
+    );
+}
+
+// ================================================================
+// VENTANAS NUEVAS — interceptadas de forma nativa
+// ================================================================
+// El handler de wry no dice si hubo gesto del usuario, así que se
+// engancha directamente el evento de cada plataforma. La decisión y
+// la creación de la pestaña se hacen fuera del hilo de UI: crear un
+// webview desde el propio evento bloquea WebView2.
+// ================================================================
+
+#[cfg(target_os = "windows")]
+fn setup_new_window_handler(webview: &tauri::Webview, tab_id: String) {
+    use webview2_com::{take_pwstr, NewWindowRequestedEventHandler};
+    use windows::core::{BOOL, PWSTR};
 
+    let app = webview.app_handle().clone();
     let _ = webview.with_webview(move |wv| {
         unsafe {
             let controller = wv.controller();
             let core = controller.CoreWebView2().unwrap();
 
-            let hscript = HSTRING::from(combined);
-            let handler = webview2_com::AddScriptToExecuteOnDocumentCreatedCompletedHandler::create(
-                Box::new(|_hr, _id| Ok(())),
+            let mut token: i64 = 0;
+            let _ = core.add_NewWindowRequested(
+                &NewWindowRequestedEventHandler::create(Box::new(move |_sender, args| {
+                    if let Some(args) = args {
+                        let mut uri_ptr = PWSTR::null();
+                        args.Uri(&mut uri_ptr)?;
+                        let url = take_pwstr(uri_ptr);
+
+                        let mut user_initiated: BOOL = false.into();
+                        let _ = args.IsUserInitiated(&mut user_initiated);
+
+                        // Nunca dejar que WebView2 abra su propia ventana
+                        args.SetHandled(true)?;
+                        handle_new_window(
+                            app.clone(),
+                            tab_id.clone(),
+                            url,
+                            user_initiated.as_bool(),
+                            false,
+                        );
+                    }
+                    Ok(())
+                })),
+                &mut token,
+            );
+        }
+    });
+}
+
+#[cfg(target_os = "linux")]
+fn setup_new_window_handler(webview: &tauri::Webview, tab_id: String) {
+    use webkit2gtk::{URIRequestExt, WebViewExt};
+
+    // GDK_CONTROL_MASK; clic central = botón 2
+    const CONTROL_MASK: u32 = 1 << 2;
+    const MIDDLE_BUTTON: u32 = 2;
+
+    let app = webview.app_handle().clone();
+    let _ = webview.with_webview(move |wv| {
+        wv.inner().connect_create(move |_, action| {
+            let url = action.request().and_then(|r| r.uri())?.to_string();
+            let background =
+                action.mouse_button() == MIDDLE_BUTTON || action.modifiers() & CONTROL_MASK != 0;
+            handle_new_window(
+                app.clone(),
+                tab_id.clone(),
+                url,
+                action.is_user_gesture(),
+                background,
             );
-            let _ = core.AddScriptToExecuteOnDocumentCreated(&hscript, &handler);
+            // Sin widget: WebKit no crea ninguna ventana por su cuenta
+            None
+        });
+    });
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "linux")))]
+fn setup_new_window_handler(_webview: &tauri::Webview, _tab_id: String) {}
+
+fn handle_new_window(
+    app: tauri::AppHandle,
+    opener_id: String,
+    url: String,
+    user_gesture: bool,
+    background: bool,
+) {
+    tauri::async_runtime::spawn(async move {
+        let opener_host = app
+            .get_webview(&opener_id)
+            .and_then(|w| w.url().ok())
+            .map(|u| popups::host_of(u.as_str()))
+            .unwrap_or_default();
+
+        let popup_state = app.state::<PopupState>().inner().clone();
+        let decision = match popup_state.lock() {
+            Ok(blocker) => blocker.decide(&opener_host, &url, user_gesture, background),
+            Err(_) => return,
+        };
+
+        match decision {
+            PopupDecision::Open { background } => {
+                let _ = open_tab_from(&app, &opener_id, url, background);
+            }
+            PopupDecision::Block(reason) => {
+                let blocked = match popup_state.lock() {
+                    Ok(mut blocker) => blocker.record(&opener_id, &url, &opener_host, reason),
+                    Err(_) => return,
+                };
+                if let Some(label) = window_label_of(&app, &opener_id) {
+                    let _ = app.emit_to(label.as_str(), "popup-blocked", blocked);
+                }
+            }
         }
     });
 }
 
-#[cfg(not(target_os = "windows"))]
-fn inject_shield_early(webview: &tauri::Webview) {
-    for script in SHIELD_SCRIPTS {
-        let _ = webview.eval(*script);
+// ================================================================
+// DESCARTE — webviews de pestañas inactivas (ver discard.rs)
+// ================================================================
+
+/// Avisa de texto escrito en un formulario sin enviar: esas pestañas no
+/// se descartan. Se evalúa solo en el documento principal (on_page_load
+/// y tab_form_state van por la pestaña), así que un formulario dentro de
+/// un iframe no la protege.
+const FORM_WATCH_SCRIPT: &str = r#"
+(function () {
+    if (window.__atomFormWatch || !window.__TAURI_INTERNALS__) return;
+    window.__atomFormWatch = true;
+    var dirty = false;
+    function set(value) {
+        if (dirty === value) return;
+        dirty = value;
+        window.__TAURI_INTERNALS__.invoke('tab_form_state', { dirty: value });
+    }
+    document.addEventListener('input', function (e) {
+        var t = e.target;
+        if (t && (t.tagName === 'INPUT' || t.tagName === 'TEXTAREA' || t.isContentEditable)) {
+            set(true);
+        }
+    }, true);
+    document.addEventListener('submit', function () { set(false); }, true);
+})();
+"#;
+
+/// Cierra el webview y deja la pestaña solo en el modelo. Se llama desde
+/// el hilo del descarte, nunca desde el principal (engine_state espera).
+fn discard_tab(app: &tauri::AppHandle, tab_id: &str) {
+    let Some(webview) = app.get_webview(tab_id) else {
+        return;
+    };
+    // Antes de cerrar: al volver a activarla, load_tab la recupera
+    keep_engine_state(tab_id, engine_state(&webview));
+    {
+        let state = app.state::<TabState>();
+        let Ok(manager) = state.lock() else {
+            return;
+        };
+        // Desde la pasada se ha podido activar, ponerse a sonar o
+        // empezar a rellenar un formulario. Con el lock tomado, nadie la
+        // activa mientras se cierra
+        let idle = manager
+            .window_of(tab_id)
+            .zip(manager.get(tab_id))
+            .is_some_and(|(window, tab)| discard::can_discard(window, tab));
+        if !idle {
+            drop(manager);
+            take_engine_state(tab_id);
+            return;
+        }
+        let _ = webview.close();
     }
+    if let Some(diagnostics) = app.try_state::<DiagnosticsState>() {
+        if let Ok(mut diagnostics) = diagnostics.lock() {
+            diagnostics.forget_tab(tab_id);
+        }
+    }
+    forget_shield_early(app, tab_id);
+    update_tab(app, tab_id, |tab| {
+        tab.discarded = true;
+        tab.loading = false;
+        tab.audible = false;
+        tab.form_dirty = false;
+    });
+}
+
+/// Una pasada del descarte; la lanza cada CHECK_INTERVAL un hilo propio.
+fn discard_idle_tabs(app: &tauri::AppHandle) {
+    let (Some(discarder), Some(state)) =
+        (app.try_state::<DiscardState>(), app.try_state::<TabState>())
+    else {
+        return;
+    };
+    let Ok(discarder) = discarder.lock() else {
+        return;
+    };
+    let memory = discarder
+        .watches_memory()
+        .then(discard::memory_mb)
+        .flatten();
+    let candidates = match state.lock() {
+        Ok(manager) => discarder.candidates(&manager, memory, std::time::Instant::now()),
+        Err(_) => return,
+    };
+    drop(discarder);
+
+    for tab_id in candidates {
+        discard_tab(app, &tab_id);
+    }
+}
+
+// ================================================================
+// SESIÓN — guardado continuo y restauración (ver session.rs)
+// ================================================================
+// Se guarda en cada cambio de pestañas o de ventana; session.rs agrupa
+// las escrituras. Al restaurar solo se carga la pestaña activa: las
+// demás crean su webview al activarlas.
+// ================================================================
+
+fn session_snapshot(app: &tauri::AppHandle) -> Option<Session> {
+    let state = app.state::<TabState>();
+    let windows: Vec<(SessionWindow, Option<FullscreenGeometry>)> = {
+        let manager = state.lock().ok()?;
+        // Solo quedan ventanas privadas: vale la última sesión guardada
+        if manager.windows.iter().all(|w| w.private) {
+            return None;
+        }
+        manager
+            .windows
+            .iter()
+            .filter(|w| !w.private)
+            .map(|w| {
+                let saved = SessionWindow {
+                    label: w.label.clone(),
+                    tabs: w.tabs.iter().map(SessionTab::from_tab).collect(),
+                    active: w.active_tab.as_deref().and_then(|id| w.index_of(id)),
+                    geometry: None,
+                };
+                // En pantalla completa la geometría que vale es la de antes
+                let fullscreen = (w.is_fullscreen || w.page_fullscreen.is_some())
+                    .then_some((w.pre_fs_pos, w.pre_fs_size));
+                (saved, fullscreen)
+            })
+            .collect()
+    };
+
+    let windows = windows
+        .into_iter()
+        .map(|(mut saved, fullscreen)| {
+            saved.geometry = app
+                .get_window(&saved.label)
+                .and_then(|win| window_geometry(&win, fullscreen));
+            saved
+        })
+        .collect();
+
+    Some(Session {
+        windows,
+        closed: Vec::new(),
+    })
+}
+
+type FullscreenGeometry = (Option<(i32, i32)>, Option<(u32, u32)>);
+
+fn window_geometry(
+    win: &tauri::Window,
+    fullscreen: Option<FullscreenGeometry>,
+) -> Option<WindowGeometry> {
+    // Minimizada la posición no es real: se queda la última buena
+    if win.is_minimized().unwrap_or(false) {
+        return None;
+    }
+    let ((x, y), (width, height)) = match fullscreen {
+        Some((Some(pos), Some(size))) => (pos, size),
+        _ => {
+            let pos = win.outer_position().ok()?;
+            let size = win.inner_size().ok()?;
+            ((pos.x, pos.y), (size.width, size.height))
+        }
+    };
+    Some(WindowGeometry {
+        x,
+        y,
+        width,
+        height,
+        maximized: win.is_maximized().unwrap_or(false),
+    })
+}
+
+fn save_session(app: &tauri::AppHandle) {
+    let Some(session) = session_snapshot(app) else {
+        return;
+    };
+    if let Some(sessions) = app.try_state::<SessionState>() {
+        if let Ok(mut sessions) = sessions.lock() {
+            sessions.save(session);
+        }
+    }
+}
+
+/// Cierre limpio: se cierra la última ventana
+fn close_session(app: &tauri::AppHandle) {
+    save_history(app);
+    let session = session_snapshot(app);
+    if let Some(sessions) = app.try_state::<SessionState>() {
+        if let Ok(mut sessions) = sessions.lock() {
+            sessions.close(session);
+        }
+    }
+}
+
+/// Añade las pestañas de `session` y carga solo la activa de cada
+/// ventana. La primera ventana guardada va a la principal si sigue
+/// abierta; las demás se crean. Crea webviews: no llamar desde el hilo
+/// principal.
+fn restore_session(app: &tauri::AppHandle, session: Session) -> Result<(), String> {
+    let main_open = app
+        .state::<TabState>()
+        .lock()
+        .map_err(|e| e.to_string())?
+        .window(MAIN_WINDOW)
+        .is_some();
+    for (i, saved) in session.windows.into_iter().enumerate() {
+        let label = if i == 0 && main_open {
+            MAIN_WINDOW.to_string()
+        } else {
+            create_browser_window(app, false)?
+        };
+        restore_window(app, &label, saved)?;
+    }
+    save_session(app);
+    Ok(())
+}
+
+fn restore_window(app: &tauri::AppHandle, label: &str, saved: SessionWindow) -> Result<(), String> {
+    if let (Some(geometry), Some(win)) = (saved.geometry, app.get_window(label)) {
+        let _ = win.set_position(tauri::PhysicalPosition::new(geometry.x, geometry.y));
+        let _ = win.set_size(tauri::PhysicalSize::new(geometry.width, geometry.height));
+        if geometry.maximized {
+            let _ = win.maximize();
+        }
+    }
+
+    let state = app.state::<TabState>();
+    let (ids, tabs) = {
+        let mut manager = state.lock().map_err(|e| e.to_string())?;
+        let mut ids = Vec::new();
+        for tab in saved.tabs {
+            let id = manager.new_id();
+            let container = tab
+                .container
+                .as_deref()
+                .and_then(|c| container_by_id(app, c));
+            let mut tab = tab.into_tab(id.clone());
+            tab.container = container;
+            manager
+                .window_mut(label)
+                .ok_or("Window not found")?
+                .insert(tab);
+            ids.push(id);
+        }
+        let window = manager.window(label).ok_or("Window not found")?;
+        (ids, window.tabs.clone())
+    };
+    let _ = app.emit_to(label, "session-restored", tabs);
+    for id in &ids {
+        tab_created(app, id, false);
+    }
+
+    let active = saved
+        .active
+        .and_then(|i| ids.get(i))
+        .or(ids.first())
+        .ok_or("Empty session")?;
+    load_tab(app, active, false)
+}
+
+// ================================================================
+// VENTANAS — cada una con su barra (index.html) y sus pestañas
+// ================================================================
+
+/// Crea una ventana vacía y la registra. Crea un webview: no llamar
+/// desde el hilo principal.
+fn create_browser_window(app: &tauri::AppHandle, private: bool) -> Result<String, String> {
+    let state = app.state::<TabState>();
+    let label = state.lock().map_err(|e| e.to_string())?.add_window(private);
+
+    let built = tauri::WebviewWindowBuilder::new(app, &label, WebviewUrl::App("index.html".into()))
+        .title("Atom Browser")
+        .inner_size(1200.0, 800.0)
+        .decorations(false)
+        .background_color(tauri::window::Color(0, 0, 0, 255))
+        .build();
+    if let Err(e) = built {
+        if let Ok(mut manager) = state.lock() {
+            manager.remove_window(&label);
+        }
+        return Err(e.to_string());
+    }
+    Ok(label)
+}
+
+/// Ventana en la que está la pestaña
+fn window_label_of(app: &tauri::AppHandle, tab_id: &str) -> Option<String> {
+    let state = app.state::<TabState>();
+    let manager = state.lock().ok()?;
+    Some(manager.window_of(tab_id)?.label.clone())
+}
+
+/// Webviews a la vista en la ventana: la activa o las dos mitades
+fn visible_webviews(app: &tauri::AppHandle, label: &str) -> Vec<tauri::Webview> {
+    let visible = {
+        let state = app.state::<TabState>();
+        let Ok(manager) = state.lock() else {
+            return Vec::new();
+        };
+        manager
+            .window(label)
+            .map(|w| w.visible())
+            .unwrap_or_default()
+    };
+    visible
+        .iter()
+        .filter_map(|id| app.get_webview(id))
+        .collect()
+}
+
+/// Rectángulo de cada pestaña de la ventana (ver layout.rs)
+fn window_layout(win: &tauri::Window) -> Vec<(String, Rect)> {
+    let (Ok(size), Ok(scale_factor)) = (win.inner_size(), win.scale_factor()) else {
+        return Vec::new();
+    };
+    let state = win.state::<TabState>();
+    let Ok(manager) = state.lock() else {
+        return Vec::new();
+    };
+    let Some(window) = manager.window(win.label()) else {
+        return Vec::new();
+    };
+    let frame = Frame {
+        width: size.width,
+        height: size.height,
+        scale_factor,
+        toolbar_height: BARRA_ALTURA,
+        fullscreen: window.is_fullscreen,
+        sidebars: window.sidebars,
+    };
+    layout::tab_rects(&frame, window)
+}
+
+/// Coloca los webviews de las pestañas de la ventana
+fn layout_tabs(win: &tauri::Window) {
+    for (tab_id, rect) in window_layout(win) {
+        if let Some(webview) = win.get_webview(&tab_id) {
+            let _ = webview.set_bounds(tauri::Rect {
+                position: tauri::LogicalPosition::new(rect.x, rect.y).into(),
+                size: tauri::LogicalSize::new(rect.width, rect.height).into(),
+            });
+        }
+    }
+}
+
+/// Tras cambiar la vista dividida: recoloca los webviews y avisa a la
+/// UI para que ponga el divisor.
+fn split_changed(app: &tauri::AppHandle, label: &str) {
+    let Some(win) = app.get_window(label) else {
+        return;
+    };
+    layout_tabs(&win);
+    let split = {
+        let state = app.state::<TabState>();
+        let Ok(manager) = state.lock() else {
+            return;
+        };
+        manager.window(label).and_then(|w| w.split.clone())
+    };
+    let _ = app.emit_to(label, "split-changed", split);
+}
+
+/// Ventana cerrada que no era la última: sus pestañas se van con ella.
+fn forget_window(app: &tauri::AppHandle, label: &str) {
+    let removed = {
+        let state = app.state::<TabState>();
+        let Ok(mut manager) = state.lock() else {
+            return;
+        };
+        manager.remove_window(label)
+    };
+    let Some(window) = removed else {
+        return;
+    };
+    for tab in &window.tabs {
+        if let Some(popups) = app.try_state::<PopupState>() {
+            if let Ok(mut blocker) = popups.lock() {
+                blocker.forget_tab(&tab.id);
+            }
+        }
+        if let Some(diagnostics) = app.try_state::<DiagnosticsState>() {
+            if let Ok(mut diagnostics) = diagnostics.lock() {
+                diagnostics.forget_tab(&tab.id);
+            }
+        }
+        forget_shield_early(app, &tab.id);
+    }
+    if window.private {
+        wipe_private_data(app);
+    }
+    save_session(app);
+}
+
+// ================================================================
+// PICTURE-IN-PICTURE — una pestaña en su propia ventana flotante
+// ================================================================
+// La pestaña sigue en la barra de su ventana y en TabManager con el
+// mismo id; solo su webview pasa (reparent) a una ventana sin marco y
+// siempre encima, la de tauri.conf.json pero con pip.html en lugar de
+// la barra: una franja para arrastrarla y acoplarla. Acoplarla la
+// devuelve a su ventana, a su sitio en la barra, y la activa.
+// ================================================================
+
+const PIP_WIDTH: f64 = 480.0;
+const PIP_HEIGHT: f64 = 270.0;
+/// Franja de pip.html sobre la página
+const PIP_BAR_HEIGHT: f64 = 24.0;
+/// Separación de la esquina inferior derecha de la pantalla
+const PIP_MARGIN: f64 = 24.0;
+
+fn pip_label(tab_id: &str) -> String {
+    format!("pip-{tab_id}")
+}
+
+fn pip_tab_of(label: &str) -> Option<&str> {
+    label.strip_prefix("pip-")
+}
+
+fn is_pip(app: &tauri::AppHandle, tab_id: &str) -> bool {
+    let state = app.state::<TabState>();
+    let Ok(manager) = state.lock() else {
+        return false;
+    };
+    manager.get(tab_id).is_some_and(|t| t.pip)
+}
+
+fn emit_tab_updated(app: &tauri::AppHandle, tab_id: &str) {
+    if let Some((label, _, tab)) = tab_snapshot(app, tab_id) {
+        let _ = app.emit_to(label.as_str(), "tab-updated", tab);
+    }
+}
+
+/// La ventana de tauri.conf.json, pequeña y encima de todo, en la esquina
+/// inferior derecha de la pantalla de `source`.
+fn create_pip_window(
+    app: &tauri::AppHandle,
+    source: &tauri::Window,
+    tab_id: &str,
+) -> Result<tauri::WebviewWindow, String> {
+    let mut config = app
+        .config()
+        .app
+        .windows
+        .first()
+        .cloned()
+        .ok_or("No window configuration")?;
+    config.label = pip_label(tab_id);
+    config.url = WebviewUrl::App("pip.html".into());
+    config.title = "Atom Browser".to_string();
+    config.width = PIP_WIDTH;
+    config.height = PIP_HEIGHT;
+    config.always_on_top = true;
+    config.skip_taskbar = true;
+    config.center = false;
+    if let Ok(Some(monitor)) = source.current_monitor() {
+        let scale = monitor.scale_factor();
+        let origin = monitor.position().to_logical::<f64>(scale);
+        let size = monitor.size().to_logical::<f64>(scale);
+        config.x = Some(origin.x + size.width - PIP_WIDTH - PIP_MARGIN);
+        config.y = Some(origin.y + size.height - PIP_HEIGHT - PIP_MARGIN);
+    }
+    tauri::WebviewWindowBuilder::from_config(app, &config)
+        .and_then(|builder| builder.build())
+        .map_err(|e| e.to_string())
+}
+
+/// El webview de la pestaña ocupa la ventana flotante bajo la franja
+fn layout_pip(win: &tauri::Window, tab_id: &str) {
+    let (Ok(size), Ok(scale_factor)) = (win.inner_size(), win.scale_factor()) else {
+        return;
+    };
+    let size = size.to_logical::<f64>(scale_factor);
+    if let Some(webview) = win.app_handle().get_webview(tab_id) {
+        let _ = webview.set_bounds(tauri::Rect {
+            position: tauri::LogicalPosition::new(0.0, PIP_BAR_HEIGHT).into(),
+            size: tauri::LogicalSize::new(size.width, (size.height - PIP_BAR_HEIGHT).max(0.0))
+                .into(),
+        });
+    }
+}
+
+/// async: puede tener que crear el webview, y desde el hilo principal
+/// bloquea en Windows.
+#[tauri::command]
+async fn pop_out_tab(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, TabState>,
+    tab_id: String,
+) -> Result<(), String> {
+    // Descartada o sin cargar: se carga primero en su ventana
+    if app.get_webview(&tab_id).is_none() {
+        load_tab(&app, &tab_id, true)?;
+    }
+    let webview = app.get_webview(&tab_id).ok_or("Tab not found")?;
+
+    let (label, new_active, previous) = {
+        let mut manager = state.lock().map_err(|e| e.to_string())?;
+        let window = manager.window_of_mut(&tab_id).ok_or("Tab not found")?;
+        // Para deshacerlo si no se puede crear la ventana flotante
+        let previous = (
+            window.active_tab.clone(),
+            window.split.clone(),
+            window.page_fullscreen.clone(),
+        );
+        let new_active = window.pop_out(&tab_id)?;
+        (window.label.clone(), new_active, previous)
+    };
+    let had_split = previous.1.is_some();
+    let had_fullscreen = previous.2.is_some();
+    let source = app.get_window(&label).ok_or("Window not found")?;
+    let pip = create_pip_window(&app, &source, &tab_id).and_then(|pip| {
+        webview
+            .reparent(&pip.as_ref().window())
+            .map_err(|e| e.to_string())?;
+        Ok(pip)
+    });
+    let pip = match pip {
+        Ok(pip) => pip,
+        Err(e) => {
+            if let Ok(mut manager) = state.lock() {
+                if let Some(window) = manager.window_of_mut(&tab_id) {
+                    let _ = window.dock(&tab_id);
+                    (window.active_tab, window.split, window.page_fullscreen) = previous;
+                }
+            }
+            if let Some(pip) = app.get_window(&pip_label(&tab_id)) {
+                let _ = pip.destroy();
+            }
+            for view in visible_webviews(&app, &label) {
+                let _ = view.show();
+            }
+            layout_tabs(&source);
+            return Err(e);
+        }
+    };
+    layout_pip(&pip.as_ref().window(), &tab_id);
+    let _ = webview.show();
+
+    if let Some(new_active) = &new_active {
+        if let Some(view) = app.get_webview(new_active) {
+            let _ = view.show();
+        } else {
+            load_tab(&app, new_active, false)?;
+        }
+        tab_activated(&app, new_active, Some(tab_id.clone()));
+    }
+    if had_split {
+        split_changed(&app, &label);
+    }
+    if had_fullscreen {
+        apply_fullscreen(&app, &label);
+    }
+    emit_tab_updated(&app, &tab_id);
+    Ok(())
+}
+
+/// Devuelve el webview a la ventana de la pestaña, oculto, y cierra la
+/// flotante. Enseñarlo y activarlo es cosa de quien llama.
+fn return_pip(app: &tauri::AppHandle, tab_id: &str) -> Result<tauri::Window, String> {
+    let label = {
+        let state = app.state::<TabState>();
+        let mut manager = state.lock().map_err(|e| e.to_string())?;
+        let window = manager.window_of_mut(tab_id).ok_or("Tab not found")?;
+        window.dock(tab_id)?;
+        window.label.clone()
+    };
+    let win = app.get_window(&label).ok_or("Window not found")?;
+    if let Some(webview) = app.get_webview(tab_id) {
+        let _ = webview.hide();
+        webview.reparent(&win).map_err(|e| e.to_string())?;
+    }
+    // destroy y no close: cerrarla a mano la acopla (ver on_window_event)
+    if let Some(pip) = app.get_window(&pip_label(tab_id)) {
+        let _ = pip.destroy();
+    }
+    layout_tabs(&win);
+    emit_tab_updated(app, tab_id);
+    Ok(win)
+}
+
+/// Vuelve a su ventana y a su sitio en la barra, ya activa
+#[tauri::command]
+async fn dock_tab(app: tauri::AppHandle, tab_id: String) -> Result<(), String> {
+    return_pip(&app, &tab_id)?;
+    activate_tab(&app, &tab_id)
+}
+
+/// Al desaparecer una ventana se van con ella las flotantes de sus pestañas
+fn close_pip_windows(app: &tauri::AppHandle, label: &str) {
+    let ids: Vec<String> = {
+        let state = app.state::<TabState>();
+        let Ok(manager) = state.lock() else {
+            return;
+        };
+        manager
+            .window(label)
+            .map(|w| {
+                w.tabs
+                    .iter()
+                    .filter(|t| t.pip)
+                    .map(|t| t.id.clone())
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+    for id in ids {
+        if let Some(pip) = app.get_window(&pip_label(&id)) {
+            let _ = pip.destroy();
+        }
+    }
+}
+
+/// Sin pestañas privadas abiertas se borran sus datos, en cuanto el
+/// motor suelte los ficheros. Si entretanto se abre otra, se deja.
+fn wipe_private_data(app: &tauri::AppHandle) {
+    let Ok(profile) = app.path().app_data_dir() else {
+        return;
+    };
+    let dir = private::data_dir(&profile);
+    let app = app.clone();
+    std::thread::spawn(move || {
+        for _ in 0..private::WIPE_ATTEMPTS {
+            std::thread::sleep(private::WIPE_RETRY);
+            let in_use = app
+                .state::<TabState>()
+                .lock()
+                .map(|manager| manager.has_private_tabs())
+                .unwrap_or(true);
+            if in_use || private::wipe(&dir).is_ok() {
+                return;
+            }
+        }
+    });
+}
+
+// ================================================================
+// COMANDOS TAURI
+// ================================================================
+
+/// Las páginas también pueden invocar comandos: los que leen o tocan
+/// historial, marcadores o pestañas solo se aceptan desde la barra de una
+/// ventana (su webview tiene la etiqueta de la ventana).
+fn require_ui(webview: &tauri::Webview) -> Result<(), String> {
+    let state = webview.state::<TabState>();
+    let manager = state.lock().map_err(|e| e.to_string())?;
+    if manager.window(webview.label()).is_none() {
+        return Err("Not allowed from a page".to_string());
+    }
+    Ok(())
+}
+
+/// `opener`: pestaña desde la que se abre, si viene de un enlace o de
+/// window.open. Decide dónde se coloca y a cuál se vuelve al cerrarla.
+/// `container`: el pedido explícitamente. Si no, el de la regla del host
+/// y si no hay regla, el del opener.
+fn open_tab(
+    app: &tauri::AppHandle,
+    window: &str,
+    url: Option<String>,
+    background: bool,
+    opener: Option<String>,
+    container: Option<String>,
+) -> Result<String, String> {
+    let state = app.state::<TabState>();
+    let url = url
+        .filter(|u| !u.is_empty())
+        .unwrap_or_else(|| "atom://home".to_string());
+    let requested = container.is_some();
+    let explicit = match container {
+        Some(id) => Some(container_by_id(app, &id).ok_or("Container not found")?),
+        None => None,
+    };
+    let ruled = explicit.or_else(|| container_for_url(app, &url));
+    let tab_id = {
+        let mut manager = state.lock().map_err(|e| e.to_string())?;
+        let id = manager.new_id();
+        let inherited = opener
+            .as_deref()
+            .and_then(|o| manager.get(o))
+            .and_then(|o| o.container.clone());
+        let window = manager.window_mut(window).ok_or("Window not found")?;
+        let mut tab = Tab::new(id.clone(), url, opener);
+        // Las privadas tienen sus propios datos: ahí no hay contenedores
+        if !window.private {
+            tab.container = ruled.or(inherited);
+        } else if requested {
+            return Err("Containers are not available in private windows".to_string());
+        }
+        window.insert(tab);
+        id
+    };
+    tab_created(app, &tab_id, !background);
+
+    if let Err(e) = load_tab(app, &tab_id, background) {
+        remove_from_model(app, &tab_id);
+        return Err(e);
+    }
+    save_session(app);
+    Ok(tab_id)
+}
+
+/// Pestaña abierta por otra (enlace, window.open): va a su misma ventana
+fn open_tab_from(
+    app: &tauri::AppHandle,
+    opener_id: &str,
+    url: String,
+    background: bool,
+) -> Result<String, String> {
+    let label = window_label_of(app, opener_id).ok_or("Tab not found")?;
+    open_tab(
+        app,
+        &label,
+        Some(url),
+        background,
+        Some(opener_id.to_string()),
+        None,
+    )
+}
+
+/// Deshace el alta de una pestaña cuyo webview no se pudo crear
+fn remove_from_model(app: &tauri::AppHandle, tab_id: &str) {
+    let removed = {
+        let state = app.state::<TabState>();
+        let Ok(mut manager) = state.lock() else {
+            return;
+        };
+        let Some(window) = manager.window_of_mut(tab_id) else {
+            return;
+        };
+        let label = window.label.clone();
+        window.remove(tab_id).map(|tab| (label, tab))
+    };
+    if let Some((window, tab)) = removed {
+        emit_tab_event(app, TabEvent::Closed { tab, window });
+    }
+}
+
+/// Pestaña fijada que navega a otro origen, con el ajuste activado: la
+/// navegación se abre en una pestaña nueva. `current` es la página de la
+/// que sale; sin ella, la URL del modelo.
+fn leaves_pinned_tab(
+    app: &tauri::AppHandle,
+    tab_id: &str,
+    current: Option<&str>,
+    url: &tauri::Url,
+) -> bool {
+    if !matches!(url.scheme(), "http" | "https") {
+        return false;
+    }
+    let enabled = app
+        .try_state::<SettingsState>()
+        .and_then(|settings| Some(settings.lock().ok()?.get().pinned_cross_origin_new_tab))
+        .unwrap_or(false);
+    if !enabled {
+        return false;
+    }
+    let state = app.state::<TabState>();
+    let Ok(manager) = state.lock() else {
+        return false;
+    };
+    manager.get(tab_id).is_some_and(|tab| {
+        tab.pinned
+            && !tauri::Url::parse(current.unwrap_or(&tab.url))
+                .is_ok_and(|current| current.origin() == url.origin())
+    })
+}
+
+/// Navegación del documento principal que no se queda en la pestaña
+/// (fijada que sale de su origen, host de otro contenedor). En la
+/// navegación inicial aún no hay webview: la pestaña ya se abrió en el
+/// contenedor que tocaba.
+fn opens_elsewhere(
+    app: &tauri::AppHandle,
+    tab_id: &str,
+    current: Option<&str>,
+    url: &tauri::Url,
+) -> bool {
+    leaves_pinned_tab(app, tab_id, current, url)
+        || (app.get_webview(tab_id).is_some() && leaves_container(app, tab_id, url))
+}
+
+/// Desde el hilo principal no se puede crear el webview
+fn open_elsewhere(app: &tauri::AppHandle, tab_id: &str, url: &tauri::Url) {
+    let (app, url, opener) = (app.clone(), url.to_string(), tab_id.to_string());
+    tauri::async_runtime::spawn(async move {
+        let _ = open_tab_from(&app, &opener, url, false);
+    });
+}
+
+fn container_by_id(app: &tauri::AppHandle, id: &str) -> Option<Container> {
+    let containers = app.try_state::<ContainerState>()?;
+    let containers = containers.lock().ok()?;
+    containers.get(id).cloned()
+}
+
+fn container_for_url(app: &tauri::AppHandle, url: &str) -> Option<Container> {
+    let containers = app.try_state::<ContainerState>()?;
+    let containers = containers.lock().ok()?;
+    containers.for_url(url).cloned()
+}
+
+/// Navegación a un host con regla desde una pestaña de otro contenedor:
+/// se abre en una pestaña nueva del suyo.
+fn leaves_container(app: &tauri::AppHandle, tab_id: &str, url: &tauri::Url) -> bool {
+    if !matches!(url.scheme(), "http" | "https") {
+        return false;
+    }
+    let Some(ruled) = container_for_url(app, url.as_str()) else {
+        return false;
+    };
+    let state = app.state::<TabState>();
+    let Ok(manager) = state.lock() else {
+        return false;
+    };
+    !manager.is_private(tab_id)
+        && manager
+            .get(tab_id)
+            .is_some_and(|tab| tab.container.as_ref().map(|c| &c.id) != Some(&ruled.id))
+}
+
+/// La home se guarda como "atom://home" o con la URL local desde la que
+/// la sirve Tauri; todo lo demás se carga tal cual.
+fn webview_url_for(url: &str) -> WebviewUrl {
+    match tauri::Url::parse(url) {
+        Ok(u)
+            if u.scheme() == "atom"
+                || u.scheme() == "tauri"
+                || u.host_str() == Some("tauri.localhost") =>
+        {
+            WebviewUrl::App("home.html".into())
+        }
+        Ok(u) => WebviewUrl::External(u),
+        Err(_) => WebviewUrl::External("about:blank".parse().unwrap()),
+    }
+}
+
+/// Hace activa una pestaña con webview y oculta lo que deja de verse. Si
+/// con ello termina la vista dividida o la pantalla completa de otra
+/// página, la ventana se recoloca.
+fn activate_tab(app: &tauri::AppHandle, tab_id: &str) -> Result<(), String> {
+    // La de la ventana flotante vuelve a la barra
+    if is_pip(app, tab_id) {
+        return_pip(app, tab_id)?;
+    }
+    let state = app.state::<TabState>();
+    let (label, previous, hidden, split_ended, fullscreen_ended) = {
+        let mut manager = state.lock().map_err(|e| e.to_string())?;
+        let window = manager.window_of_mut(tab_id).ok_or("Tab not found")?;
+        let previous = window.active_tab.clone();
+        let had_split = window.split.is_some();
+        let fullscreen = window.page_fullscreen.clone();
+        let hidden = window.activate(tab_id);
+        (
+            window.label.clone(),
+            previous,
+            hidden,
+            had_split && window.split.is_none(),
+            fullscreen.filter(|_| window.page_fullscreen.is_none()),
+        )
+    };
+    for old in hidden {
+        if let Some(view) = app.get_webview(&old) {
+            let _ = view.hide();
+        }
+    }
+    if let Some(view) = app.get_webview(tab_id) {
+        let _ = view.show();
+    }
+    if let Some(old) = fullscreen_ended {
+        // Que la página sepa que ya no está en pantalla completa
+        if let Some(view) = app.get_webview(&old) {
+            let _ = view.eval(EXIT_FULLSCREEN_SCRIPT);
+        }
+        apply_fullscreen(app, &label);
+    }
+    if split_ended {
+        split_changed(app, &label);
+    }
+    if previous.as_deref() != Some(tab_id) {
+        tab_activated(app, tab_id, previous);
+    }
+    Ok(())
+}
+
+// ================================================================
+// HISTORIAL DEL MOTOR — lista atrás/adelante de cada webview
+// ================================================================
+// El modelo solo guarda URLs (Tab.history); la lista del motor lleva
+// además el scroll y el estado de cada entrada. Cuando un webview se va
+// a volver a crear (pestaña cerrada y reabierta, movida, descartada) se
+// guarda su estado serializado y load_tab se lo devuelve al nuevo.
+//
+// WebKitGTK lo expone (session_state / restore_session_state). WebView2
+// no tiene API para leer ni rellenar su lista: ahí el webview nuevo
+// empieza en la página actual, sin atrás ni adelante.
+// ================================================================
+
+/// Estados a la espera de que load_tab cree el webview, por pestaña
+fn saved_engine_states() -> &'static Mutex<HashMap<String, Vec<u8>>> {
+    static STATES: std::sync::OnceLock<Mutex<HashMap<String, Vec<u8>>>> =
+        std::sync::OnceLock::new();
+    STATES.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// Estado serializado del motor. Espera al hilo principal: no llamar
+/// desde él (ver capture_engine_state).
+#[cfg(target_os = "linux")]
+fn engine_state(webview: &tauri::Webview) -> Option<Vec<u8>> {
+    use webkit2gtk::WebViewExt;
+
+    // El estado de sesión no es Send: pasa serializado
+    let (send, receive) = std::sync::mpsc::channel();
+    webview
+        .with_webview(move |wv| {
+            let bytes = wv
+                .inner()
+                .session_state()
+                .and_then(|s| s.serialize())
+                .map(|b| b.to_vec());
+            let _ = send.send(bytes);
+        })
+        .ok()?;
+    receive
+        .recv_timeout(std::time::Duration::from_secs(1))
+        .ok()
+        .flatten()
+}
+
+#[cfg(not(target_os = "linux"))]
+fn engine_state(_webview: &tauri::Webview) -> Option<Vec<u8>> {
+    None
+}
+
+/// engine_state desde un comando async
+async fn capture_engine_state(app: &tauri::AppHandle, tab_id: &str) -> Option<Vec<u8>> {
+    let webview = app.get_webview(tab_id)?;
+    tauri::async_runtime::spawn_blocking(move || engine_state(&webview))
+        .await
+        .ok()
+        .flatten()
+}
+
+/// Para el próximo load_tab de `tab_id`
+fn keep_engine_state(tab_id: &str, state: Option<Vec<u8>>) {
+    if let (Some(state), Ok(mut states)) = (state, saved_engine_states().lock()) {
+        states.insert(tab_id.to_string(), state);
+    }
+}
+
+fn take_engine_state(tab_id: &str) -> Option<Vec<u8>> {
+    saved_engine_states().lock().ok()?.remove(tab_id)
+}
+
+/// Devuelve a un webview recién creado la lista que tenía su pestaña y
+/// vuelve a cargar la entrada actual.
+#[cfg(target_os = "linux")]
+fn restore_engine_state(webview: &tauri::Webview, state: Vec<u8>) {
+    use webkit2gtk::{BackForwardListExt, WebViewExt, WebViewSessionState};
+
+    let _ = webview.with_webview(move |wv| {
+        let view = wv.inner();
+        view.restore_session_state(&WebViewSessionState::new(&state.into()));
+        if let Some(item) = view.back_forward_list().and_then(|l| l.current_item()) {
+            view.go_to_back_forward_list_item(&item);
+        }
+    });
+}
+
+#[cfg(not(target_os = "linux"))]
+fn restore_engine_state(_webview: &tauri::Webview, _state: Vec<u8>) {}
+
+/// Crea el webview de una pestaña que ya está en el modelo: recién
+/// abierta o restaurada sin cargar. Si no va en segundo plano pasa a
+/// ser la activa.
+fn load_tab(app: &tauri::AppHandle, tab_id: &str, background: bool) -> Result<(), String> {
+    let state = app.state::<TabState>();
+    let (stored_url, label, is_private, container) = {
+        let manager = state.lock().map_err(|e| e.to_string())?;
+        let window = manager.window_of(tab_id).ok_or("Tab not found")?;
+        let tab = manager.get(tab_id).ok_or("Tab not found")?;
+        (
+            tab.url.clone(),
+            window.label.clone(),
+            window.private,
+            tab.container.as_ref().map(|c| c.id.clone()),
+        )
+    };
+
+    let win = app.get_window(&label).ok_or("Window not found")?;
+    let engine_state = take_engine_state(tab_id);
+
+    let tab_id = tab_id.to_string();
+    let load_tab_id = tab_id.clone();
+    let title_tab_id = tab_id.clone();
+
+    let webview_url = webview_url_for(&stored_url);
+
+    let mut builder = tauri::webview::WebviewBuilder::new(&tab_id, webview_url).auto_resize();
+    if is_private {
+        let profile = app.path().app_data_dir().map_err(|e| e.to_string())?;
+        builder = builder
+            .incognito(true)
+            .data_directory(private::data_dir(&profile));
+    } else if let Some(container) = container {
+        let containers = app.state::<ContainerState>();
+        let dir = containers
+            .lock()
+            .map_err(|e| e.to_string())?
+            .data_dir(&container);
+        builder = builder.data_directory(dir);
+    }
+
+    // En Linux es decide-policy, que salta también para los iframes: lo
+    // del documento principal va en load-changed (ver
+    // setup_tab_state_watchers)
+    #[cfg(not(target_os = "linux"))]
+    {
+        let (nav_app, nav_tab_id) = (app.clone(), tab_id.clone());
+        builder = builder.on_navigation(move |url| {
+            if opens_elsewhere(&nav_app, &nav_tab_id, None, url) {
+                open_elsewhere(&nav_app, &nav_tab_id, url);
+                return false;
+            }
+
+            // Registrar los módulos del host de destino antes de que
+            // exista su documento. En la navegación inicial el webview
+            // aún no está registrado: eso lo cubre el paso 1 de abajo.
+            #[cfg(target_os = "windows")]
+            if let Some(webview) = nav_app.get_webview(&nav_tab_id) {
+                inject_shield_early(&webview, url.as_str());
+            }
+            true
+        });
+    }
+
+    let rect = window_layout(&win)
+        .into_iter()
+        .find(|(id, _)| *id == tab_id)
+        .map(|(_, rect)| rect)
+        .ok_or("Tab not found")?;
+    let webview = win
+        .add_child(
+            builder
+                // Solo a la ventana de la pestaña: las de una privada no
+                // se guardan en la lista de descargas
+                .on_download(move |webview, event| match event {
+                    tauri::webview::DownloadEvent::Requested { url, destination } => {
+                        let filename = destination
+                            .file_name()
+                            .map(|f| f.to_string_lossy().to_string())
+                            .unwrap_or_default();
+                        let _ = webview.app_handle().emit_to(
+                            webview.window().label(),
+                            "download-started",
+                            serde_json::json!({
+                                "id": url.to_string(),
+                                "filename": filename,
+                                "path": destination.to_string_lossy(),
+                                "private": is_private
+                            }),
+                        );
+                        true
+                    }
+                    tauri::webview::DownloadEvent::Finished { url, success, .. } => {
+                        let _ = webview.app_handle().emit_to(
+                            webview.window().label(),
+                            "download-finished",
+                            serde_json::json!({
+                                "id": url.to_string(),
+                                "success": success,
+                                "private": is_private
+                            }),
+                        );
+                        true
+                    }
+                    _ => true,
+                })
+                .on_document_title_changed(move |webview, title| {
+                    if !is_private {
+                        if let Ok(url) = webview.url() {
+                            record_title(webview.app_handle(), url.as_str(), &title);
+                        }
+                    }
+                    update_tab(webview.app_handle(), &title_tab_id, |tab| tab.title = title);
+                })
+                .on_page_load(move |webview, payload| {
+                    let loading = payload.event() == tauri::webview::PageLoadEvent::Started;
+                    update_tab(webview.app_handle(), &load_tab_id, |tab| {
+                        tab.loading = loading;
+                        if loading {
+                            tab.form_dirty = false;
+                        }
+                        tab.set_url(payload.url().as_str());
+                        if !loading {
+                            tab.default_favicon();
+                        }
+                    });
+
+                    // Documento nuevo: empieza de cero el diagnóstico del shield
+                    if payload.event() == tauri::webview::PageLoadEvent::Started {
+                        if let Some(diagnostics) = webview.try_state::<DiagnosticsState>() {
+                            if let Ok(mut diagnostics) = diagnostics.lock() {
+                                diagnostics.reset(&load_tab_id);
+                            }
+                        }
+                    }
+
+                    // Respaldo: inyectar shield por si la inyección temprana falló
+                    // Esto cubre recargas y navegaciones SPA
+                    if payload.event() == tauri::webview::PageLoadEvent::Finished {
+                        inject_shield_fallback(&webview, payload.url().as_str());
+                        let _ = webview.eval(FORM_WATCH_SCRIPT);
+                        if !is_private {
+                            record_visit(webview.app_handle(), payload.url().as_str());
+                        }
+                    }
+
+                    if let Ok(url) = webview.url() {
+                        let _ = webview.app_handle().emit_to(
+                            webview.window().label(),
+                            "url-changed",
+                            serde_json::json!({
+                                "id": load_tab_id,
+                                "url": url.to_string()
+                            }),
+                        );
+                    }
+                }),
+            tauri::LogicalPosition::new(rect.x, rect.y),
+            tauri::LogicalSize::new(rect.width, rect.height),
+        )
+        .map_err(|e| e.to_string())?;
+
+    // --- ORDEN CRÍTICO ---
+    // 1. Primero: inyección temprana del shield (antes de cualquier navegación)
+    inject_shield_early(&webview, &stored_url);
+
+    // 2. Segundo: bloqueador de red (intercepta peticiones HTTP)
+    #[cfg(target_os = "windows")]
+    crate::ad_blocker::network_blocker::setup_network_blocker(&webview);
+
+    // 3. Tercero: window.open / target=_blank → pestañas de Atom
+    setup_new_window_handler(&webview, tab_id.clone());
+
+    // 4. Estado nativo de la pestaña: historial, favicon, audio
+    setup_tab_state_watchers(&webview, tab_id.clone());
+
+    // 5. La lista atrás/adelante de su webview anterior, si la hay
+    if let Some(state) = engine_state {
+        restore_engine_state(&webview, state);
+    }
+
+    if background {
+        let _ = webview.hide();
+    } else {
+        activate_tab(app, &tab_id)?;
+    }
+    update_tab(app, &tab_id, |tab| tab.discarded = false);
+    Ok(())
+}
+
+#[tauri::command]
+async fn create_tab(
+    app: tauri::AppHandle,
+    window: tauri::Window,
+    url: Option<String>,
+    container: Option<String>,
+) -> Result<String, String> {
+    open_tab(&app, window.label(), url, false, None, container)
+}
+
+#[tauri::command]
+async fn close_tab(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, TabState>,
+    popups: tauri::State<'_, PopupState>,
+    diagnostics: tauri::State<'_, DiagnosticsState>,
+    sessions: tauri::State<'_, SessionState>,
+    tab_id: String,
+    force: Option<bool>,
+) -> Result<(), String> {
+    // Ctrl+W no cierra una fijada; la X de la pestaña pasa `force`
+    let (pinned, private) = {
+        let manager = state.lock().map_err(|e| e.to_string())?;
+        let tab = manager.get(&tab_id).ok_or("Tab not found")?;
+        (tab.pinned, manager.is_private(&tab_id))
+    };
+    if pinned && !force.unwrap_or(false) {
+        return Err("Tab is pinned".to_string());
+    }
+    // Para reabrirla con su historial. Las privadas no se recuerdan
+    let engine_state = if private {
+        None
+    } else {
+        capture_engine_state(&app, &tab_id).await
+    };
+
+    if let Ok(mut blocker) = popups.lock() {
+        blocker.forget_tab(&tab_id);
+    }
+    if let Ok(mut diagnostics) = diagnostics.lock() {
+        diagnostics.forget_tab(&tab_id);
+    }
+
+    let (closed, new_active, last_private, split_ended, fullscreen_ended) = {
+        let mut manager = state.lock().map_err(|e| e.to_string())?;
+        let window = manager.window_of_mut(&tab_id).ok_or("Tab not found")?;
+        let label = window.label.clone();
+        let was_private = window.private;
+        let had_split = window.split.is_some();
+        let was_fullscreen = window.page_fullscreen.as_ref() == Some(&tab_id);
+
+        if let Some(webview) = app.get_webview(&tab_id) {
+            let _ = webview.close();
+        }
+        if let Some(pip) = app.get_window(&pip_label(&tab_id)) {
+            let _ = pip.destroy();
+        }
+        forget_shield_early(&app, &tab_id);
+
+        let was_active = window.active_tab.as_ref() == Some(&tab_id);
+        let index = window.index_of(&tab_id).unwrap_or_default();
+        let closed = window.remove(&tab_id);
+        if let Some(tab) = closed.as_ref().filter(|_| !was_private) {
+            if let Ok(mut sessions) = sessions.lock() {
+                sessions.push_closed(ClosedTab {
+                    tab: SessionTab::from_tab(tab),
+                    index,
+                    engine_state,
+                });
+            }
+        }
+        let new_active = window.active_tab.clone().filter(|_| was_active);
+        // La otra mitad de la vista dividida se queda con todo el espacio
+        let split_ended = (had_split && window.split.is_none()).then(|| window.label.clone());
+        let fullscreen_ended = was_fullscreen.then(|| window.label.clone());
+        (
+            closed.map(|tab| (label, tab)),
+            new_active,
+            was_private && !manager.has_private_tabs(),
+            split_ended,
+            fullscreen_ended,
+        )
+    };
+    if let Some((window, tab)) = closed {
+        emit_tab_event(&app, TabEvent::Closed { tab, window });
+    }
+    if let Some(new_active) = &new_active {
+        tab_activated(&app, new_active, None);
+    }
+    if last_private {
+        wipe_private_data(&app);
+    }
+    if let Some(label) = split_ended {
+        split_changed(&app, &label);
+    }
+    if let Some(label) = fullscreen_ended {
+        apply_fullscreen(&app, &label);
+    }
+
+    if let Some(new_active) = new_active {
+        if let Some(webview) = app.get_webview(&new_active) {
+            let _ = webview.show();
+        } else {
+            // Restaurada sin cargar
+            let _ = load_tab(&app, &new_active, false);
+        }
+    }
+    save_session(&app);
+    Ok(())
+}
+
+/// Abre al lado una copia de la pestaña y la activa. async: crea el
+/// webview, y hacerlo desde el hilo principal bloquea en Windows. La
+/// copia lleva la lista atrás/adelante del original donde el motor deja
+/// leerla (ver engine_state); en Windows empieza en la página actual y el
+/// historial copiado queda solo en el modelo (sesión).
+#[tauri::command]
+async fn duplicate_tab(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, TabState>,
+    tab_id: String,
+) -> Result<String, String> {
+    let engine_state = capture_engine_state(&app, &tab_id).await;
+    let new_id = {
+        let mut manager = state.lock().map_err(|e| e.to_string())?;
+        let id = manager.new_id();
+        let window = manager.window_of_mut(&tab_id).ok_or("Tab not found")?;
+        let index = window.index_of(&tab_id).ok_or("Tab not found")?;
+        let tab = window.tabs[index].duplicate(id.clone());
+        window.insert_at(tab, index + 1);
+        id
+    };
+    tab_created(&app, &new_id, true);
+    keep_engine_state(&new_id, engine_state);
+
+    if let Err(e) = load_tab(&app, &new_id, false) {
+        remove_from_model(&app, &new_id);
+        return Err(e);
+    }
+    save_session(&app);
+    Ok(new_id)
+}
+
+/// Ctrl+Shift+T: vuelve en su posición y con su historial.
+/// Sin `index`, la última cerrada
+#[tauri::command]
+async fn reopen_closed_tab(
+    app: tauri::AppHandle,
+    webview: tauri::Webview,
+    window: tauri::Window,
+    state: tauri::State<'_, TabState>,
+    sessions: tauri::State<'_, SessionState>,
+    index: Option<usize>,
+) -> Result<String, String> {
+    require_ui(&webview)?;
+    let closed = sessions
+        .lock()
+        .map_err(|e| e.to_string())?
+        .take_closed(index.unwrap_or(0))
+        .ok_or("No closed tabs")?;
+    let container = closed
+        .tab
+        .container
+        .as_deref()
+        .and_then(|c| container_by_id(&app, c));
+    let engine_state = closed.engine_state;
+    let tab_id = {
+        let mut manager = state.lock().map_err(|e| e.to_string())?;
+        let id = manager.new_id();
+        let window = manager
+            .window_mut(window.label())
+            .ok_or("Window not found")?;
+        let mut tab = closed.tab.into_tab(id.clone());
+        if !window.private {
+            tab.container = container;
+        }
+        window.insert_at(tab, closed.index);
+        id
+    };
+    tab_created(&app, &tab_id, true);
+    keep_engine_state(&tab_id, engine_state);
+
+    if let Err(e) = load_tab(&app, &tab_id, false) {
+        remove_from_model(&app, &tab_id);
+        return Err(e);
+    }
+    save_session(&app);
+    Ok(tab_id)
+}
+
+#[tauri::command]
+fn list_recently_closed(
+    webview: tauri::Webview,
+    sessions: tauri::State<SessionState>,
+) -> Result<Vec<ClosedTab>, String> {
+    require_ui(&webview)?;
+    let sessions = sessions.lock().map_err(|e| e.to_string())?;
+    Ok(sessions.closed())
+}
+
+// ================================================================
+// HISTORIAL, MARCADORES Y BUSCADOR RÁPIDO
+// ================================================================
+
+/// Carga terminada en una pestaña que no es privada
+fn record_visit(app: &tauri::AppHandle, url: &str) {
+    if let Some(history) = app.try_state::<HistoryState>() {
+        if let Ok(mut history) = history.lock() {
+            history.visit(url);
+        }
+    }
+}
+
+fn record_title(app: &tauri::AppHandle, url: &str, title: &str) {
+    if let Some(history) = app.try_state::<HistoryState>() {
+        if let Ok(mut history) = history.lock() {
+            history.set_title(url, title);
+        }
+    }
+}
+
+fn save_history(app: &tauri::AppHandle) {
+    if let Some(history) = app.try_state::<HistoryState>() {
+        if let Ok(mut history) = history.lock() {
+            history.save_if_dirty();
+        }
+    }
+}
+
+/// La más reciente primero
+#[tauri::command]
+fn list_history(
+    webview: tauri::Webview,
+    history: tauri::State<HistoryState>,
+    limit: Option<usize>,
+) -> Result<Vec<HistoryEntry>, String> {
+    require_ui(&webview)?;
+    let history = history.lock().map_err(|e| e.to_string())?;
+    Ok(history.list(limit.unwrap_or(usize::MAX)))
+}
+
+#[tauri::command]
+fn remove_history_entry(
+    webview: tauri::Webview,
+    history: tauri::State<HistoryState>,
+    url: String,
+) -> Result<(), String> {
+    require_ui(&webview)?;
+    history.lock().map_err(|e| e.to_string())?.remove(&url);
+    Ok(())
+}
+
+#[tauri::command]
+fn clear_history(
+    webview: tauri::Webview,
+    history: tauri::State<HistoryState>,
+) -> Result<(), String> {
+    require_ui(&webview)?;
+    history.lock().map_err(|e| e.to_string())?.clear();
+    Ok(())
+}
+
+/// A todas las ventanas: cada UI pinta su estrella y su lista
+fn bookmarks_changed(app: &tauri::AppHandle, store: &Bookmarks) {
+    let _ = app.emit("bookmarks-changed", store.list());
+}
+
+#[tauri::command]
+fn list_bookmarks(
+    webview: tauri::Webview,
+    store: tauri::State<BookmarkState>,
+) -> Result<Vec<Bookmark>, String> {
+    require_ui(&webview)?;
+    let store = store.lock().map_err(|e| e.to_string())?;
+    Ok(store.list().to_vec())
+}
+
+/// Sin `title`, el de la pestaña abierta en `url`
+#[tauri::command]
+fn add_bookmark(
+    app: tauri::AppHandle,
+    webview: tauri::Webview,
+    state: tauri::State<TabState>,
+    store: tauri::State<BookmarkState>,
+    url: String,
+    title: Option<String>,
+) -> Result<(), String> {
+    require_ui(&webview)?;
+    let title = match title.filter(|t| !t.is_empty()) {
+        Some(title) => title,
+        None => state
+            .lock()
+            .map_err(|e| e.to_string())?
+            .windows
+            .iter()
+            .flat_map(|w| &w.tabs)
+            .find(|t| t.url == url && !t.title.is_empty())
+            .map(|t| t.title.clone())
+            .unwrap_or_else(|| url.clone()),
+    };
+    let mut store = store.lock().map_err(|e| e.to_string())?;
+    store.add(url, title)?;
+    bookmarks_changed(&app, &store);
+    Ok(())
+}
+
+#[tauri::command]
+fn remove_bookmark(
+    app: tauri::AppHandle,
+    webview: tauri::Webview,
+    store: tauri::State<BookmarkState>,
+    url: String,
+) -> Result<(), String> {
+    require_ui(&webview)?;
+    let mut store = store.lock().map_err(|e| e.to_string())?;
+    store.remove(&url)?;
+    bookmarks_changed(&app, &store);
+    Ok(())
+}
+
+// ================================================================
+// FAVICONS — bajados por la propia pestaña (ver favicons.rs)
+// ================================================================
+
+/// Función que se llama con (petición, iconos ya conocidos, lado). Prueba
+/// los iconos que el motor ya conoce, luego los <link> declarados (el
+/// tamaño más cercano a `size` primero) y por último /favicon.ico; el
+/// primero que baje y se pueda pintar vuelve como PNG de size×size. Con
+/// `fetch` desde la página: sus cookies y su red, y lo que su CSP deje.
+const FAVICON_SCRIPT: &str = r#"
+function (request, known, size) {
+    if (!window.__TAURI_INTERNALS__) return;
+    function done(data) {
+        window.__TAURI_INTERNALS__.invoke('favicon_fetched', { request: request, data: data });
+    }
+    function distance(link) {
+        var sizes = (link.getAttribute('sizes') || '').toLowerCase();
+        if (sizes === 'any') return 0;
+        var match = /(\d+)x\d+/.exec(sizes);
+        return match ? Math.abs(parseInt(match[1], 10) - size) : size;
+    }
+    function draw(blob) {
+        return new Promise(function (resolve, reject) {
+            var src = URL.createObjectURL(blob);
+            var img = new Image();
+            img.onload = function () {
+                var canvas = document.createElement('canvas');
+                canvas.width = canvas.height = size;
+                var scale = size / Math.max(img.naturalWidth || size, img.naturalHeight || size);
+                var w = (img.naturalWidth || size) * scale, h = (img.naturalHeight || size) * scale;
+                canvas.getContext('2d').drawImage(img, (size - w) / 2, (size - h) / 2, w, h);
+                URL.revokeObjectURL(src);
+                resolve(canvas.toDataURL('image/png'));
+            };
+            img.onerror = function () { URL.revokeObjectURL(src); reject(); };
+            img.src = src;
+        });
+    }
+    function run() {
+        var links = Array.prototype.slice.call(
+            document.querySelectorAll('link[rel~="icon" i], link[rel~="apple-touch-icon" i]'));
+        links.sort(function (a, b) { return distance(a) - distance(b); });
+        var hrefs = known.concat(links.map(function (l) { return l.href; }));
+        hrefs.push(location.origin + '/favicon.ico');
+        hrefs = hrefs.filter(function (h, i) { return h && hrefs.indexOf(h) === i; });
+        (function next(i) {
+            if (i >= hrefs.length) return done(null);
+            fetch(hrefs[i], { credentials: 'include' })
+                .then(function (r) { if (!r.ok) throw r.status; return r.blob(); })
+                .then(draw)
+                .then(done, function () { next(i + 1); });
+        })(0);
+    }
+    if (document.readyState === 'loading') {
+        document.addEventListener('DOMContentLoaded', run, { once: true });
+    } else {
+        run();
+    }
+}
+"#;
+
+/// Icono del sitio de `url` como data URL (PNG). Sale de la caché si es
+/// reciente; si no, se le pide a una pestaña abierta en ese sitio y, si
+/// no hay o falla, vale el que hubiera. Solo se baja con una pestaña
+/// viva del sitio: marcadores, historial y buscador rápido de sitios
+/// sin icono en caché se quedan sin él. Lo de pestañas privadas no se
+/// guarda en disco.
+#[tauri::command]
+async fn get_favicon(
+    app: tauri::AppHandle,
+    webview: tauri::Webview,
+    url: String,
+) -> Result<Option<String>, String> {
+    require_ui(&webview)?;
+    let Some(site) = favicons::site_of(&url) else {
+        return Ok(None);
+    };
+    let favicons = app.state::<FaviconState>();
+    let cached = favicons.lock().map_err(|e| e.to_string())?.get(&site);
+    if let Some((data_url, true)) = &cached {
+        return Ok(Some(data_url.clone()));
+    }
+    let stale = cached.map(|(data_url, _)| data_url);
+
+    let Some((tab_id, private, known)) = favicon_tab(&app, &site) else {
+        return Ok(stale);
+    };
+    let Some(webview) = app.get_webview(&tab_id) else {
+        return Ok(stale);
+    };
+    let (request, reply) = favicons.lock().map_err(|e| e.to_string())?.request(&tab_id);
+    let known = serde_json::to_string(&known).map_err(|e| e.to_string())?;
+    let _ = webview.eval(format!(
+        "({FAVICON_SCRIPT})({request}, {known}, {});",
+        favicons::ICON_SIZE
+    ));
+    let png = tauri::async_runtime::spawn_blocking(move || {
+        reply.recv_timeout(favicons::FETCH_TIMEOUT).ok().flatten()
+    })
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let mut favicons = favicons.lock().map_err(|e| e.to_string())?;
+    favicons.cancel(request);
+    match png {
+        Some(png) => {
+            if !private {
+                favicons.store(&site, &png)?;
+            }
+            Ok(Some(favicons::to_data_url(&png)))
+        }
+        None => Ok(stale),
+    }
+}
+
+/// Pestaña con webview abierta en `site`, mejor la activa de su ventana:
+/// (id, si es privada, icono que ya conoce el motor)
+fn favicon_tab(app: &tauri::AppHandle, site: &str) -> Option<(String, bool, Vec<String>)> {
+    let state = app.state::<TabState>();
+    let manager = state.lock().ok()?;
+    manager
+        .windows
+        .iter()
+        .flat_map(|w| w.tabs.iter().map(move |tab| (w, tab)))
+        .filter(|(_, tab)| !tab.discarded)
+        .filter(|(_, tab)| favicons::site_of(&tab.url).as_deref() == Some(site))
+        .max_by_key(|(w, tab)| w.active_tab.as_ref() == Some(&tab.id))
+        .map(|(w, tab)| {
+            (
+                tab.id.clone(),
+                w.private,
+                tab.favicon.iter().cloned().collect(),
+            )
+        })
+}
+
+/// Lo invoca FAVICON_SCRIPT desde la página.
+#[tauri::command]
+fn favicon_fetched(
+    webview: tauri::Webview,
+    favicons: tauri::State<FaviconState>,
+    request: u64,
+    data: Option<String>,
+) {
+    if let Ok(mut favicons) = favicons.lock() {
+        favicons.resolve(request, webview.label(), data.as_deref());
+    }
+}
+
+/// Búsqueda aproximada en pestañas abiertas, cerradas, marcadores e
+/// historial, cada resultado con lo que hay que hacer al elegirlo. Desde
+/// una ventana privada se ven sus pestañas privadas y no las normales, y
+/// al revés.
+#[tauri::command]
+fn quick_switch(
+    webview: tauri::Webview,
+    state: tauri::State<TabState>,
+    sessions: tauri::State<SessionState>,
+    bookmarks: tauri::State<BookmarkState>,
+    history: tauri::State<HistoryState>,
+    query: String,
+) -> Result<Vec<SwitchResult>, String> {
+    require_ui(&webview)?;
+    let manager = state.lock().map_err(|e| e.to_string())?;
+    let private = manager.window(webview.label()).is_some_and(|w| w.private);
+    let closed = if private {
+        Vec::new()
+    } else {
+        sessions.lock().map_err(|e| e.to_string())?.closed()
+    };
+    let bookmarks = bookmarks.lock().map_err(|e| e.to_string())?;
+    let history = history.lock().map_err(|e| e.to_string())?;
+    let now = history::now_millis();
+    let visits = |url: &str| history.get(url).map(|e| e.visits).unwrap_or(0);
+
+    let tabs = manager
+        .windows
+        .iter()
+        .filter(|w| w.private == private)
+        .flat_map(|w| w.tabs.iter().map(move |tab| (w, tab)))
+        .map(|(w, tab)| Candidate {
+            title: &tab.title,
+            url: &tab.url,
+            target: Target::Tab {
+                id: &tab.id,
+                window: &w.label,
+            },
+            last_used: now.saturating_sub(tab.last_active.elapsed().as_millis() as u64),
+            visits: visits(&tab.url),
+        });
+    let closed = closed.iter().enumerate().map(|(index, closed)| Candidate {
+        title: &closed.tab.title,
+        url: &closed.tab.url,
+        target: Target::Closed { index },
+        last_used: 0,
+        visits: visits(&closed.tab.url),
+    });
+    let marked = bookmarks.list().iter().map(|bookmark| Candidate {
+        title: &bookmark.title,
+        url: &bookmark.url,
+        target: Target::Bookmark,
+        last_used: history
+            .get(&bookmark.url)
+            .map(|e| e.last_visit)
+            .unwrap_or(bookmark.added),
+        visits: visits(&bookmark.url),
+    });
+    let visited = history.entries().map(|entry| Candidate {
+        title: &entry.title,
+        url: &entry.url,
+        target: Target::History,
+        last_used: entry.last_visit,
+        visits: entry.visits,
+    });
+    Ok(switcher::search(
+        &query,
+        tabs.chain(closed).chain(marked).chain(visited),
+        now,
+    ))
+}
+
+/// Pestañas de la ventana desde la que se llama
+#[tauri::command]
+fn list_tabs(
+    webview: tauri::Webview,
+    window: tauri::Window,
+    state: tauri::State<TabState>,
+) -> Result<Vec<Tab>, String> {
+    require_ui(&webview)?;
+    let manager = state.lock().map_err(|e| e.to_string())?;
+    let window = manager.window(window.label()).ok_or("Window not found")?;
+    Ok(window.tabs.clone())
+}
+
+#[tauri::command]
+fn list_windows(
+    webview: tauri::Webview,
+    state: tauri::State<TabState>,
+) -> Result<Vec<BrowserWindow>, String> {
+    require_ui(&webview)?;
+    let manager = state.lock().map_err(|e| e.to_string())?;
+    Ok(manager.windows.clone())
+}
+
+/// Ventana nueva, con una pestaña (la home si no hay `url`). Devuelve
+/// su etiqueta. Privada: nada suyo queda en disco ni en la sesión.
+#[tauri::command]
+async fn new_window(
+    app: tauri::AppHandle,
+    webview: tauri::Webview,
+    url: Option<String>,
+    private: Option<bool>,
+) -> Result<String, String> {
+    require_ui(&webview)?;
+    let label = create_browser_window(&app, private.unwrap_or(false))?;
+    open_tab(&app, &label, url, false, None, None)?;
+    Ok(label)
+}
+
+/// La UI no guarda historial ni descargas en una ventana privada
+#[tauri::command]
+fn is_private_window(window: tauri::Window, state: tauri::State<TabState>) -> Result<bool, String> {
+    let manager = state.lock().map_err(|e| e.to_string())?;
+    Ok(manager
+        .window(window.label())
+        .ok_or("Window not found")?
+        .private)
+}
+
+/// Espera, sin ocupar el runtime, a que se cierre el webview de
+/// `label` para poder crear otro con la misma etiqueta.
+async fn label_freed(app: &tauri::AppHandle, label: &str) -> bool {
+    let (app, label) = (app.clone(), label.to_string());
+    tauri::async_runtime::spawn_blocking(move || {
+        for _ in 0..40 {
+            if app.get_webview(&label).is_none() {
+                return true;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(50));
+        }
+        false
+    })
+    .await
+    .unwrap_or(false)
+}
+
+/// Arrastrar una pestaña a otra ventana. El webview se cambia de ventana
+/// sin recargar; si el sistema no lo permite se crea de nuevo en la
+/// destino con su historial (ver engine_state). Una ventana secundaria
+/// que se queda sin pestañas se cierra.
+#[tauri::command]
+async fn move_tab_to_window(
+    app: tauri::AppHandle,
+    webview: tauri::Webview,
+    state: tauri::State<'_, TabState>,
+    tab_id: String,
+    window: String,
+    index: Option<usize>,
+) -> Result<(), String> {
+    require_ui(&webview)?;
+    let target = app.get_window(&window).ok_or("Window not found")?;
+    if is_pip(&app, &tab_id) {
+        return_pip(&app, &tab_id)?;
+    }
+    let (source, old_target_active, old_target_visible, new_active, source_empty, split_ended) = {
+        let mut manager = state.lock().map_err(|e| e.to_string())?;
+        let target = manager.window(&window).ok_or("Window not found")?;
+        let old_target_active = target.active_tab.clone();
+        let old_target_visible = target.visible();
+        let had_split: Vec<String> = manager
+            .windows
+            .iter()
+            .filter(|w| w.split.is_some())
+            .map(|w| w.label.clone())
+            .collect();
+        let source = manager.transfer(&tab_id, &window, index.unwrap_or(usize::MAX))?;
+        // Sacar una pestaña de la vista dividida, o activar otra, la termina
+        let split_ended: Vec<String> = had_split
+            .into_iter()
+            .filter(|label| manager.window(label).is_some_and(|w| w.split.is_none()))
+            .collect();
+        let from = manager.window(&source).ok_or("Window not found")?;
+        (
+            source.clone(),
+            old_target_active,
+            old_target_visible,
+            from.active_tab.clone(),
+            from.tabs.is_empty(),
+            split_ended,
+        )
+    };
+    tab_moved(&app, &tab_id, source.clone());
+    if source == window {
+        for label in &split_ended {
+            split_changed(&app, label);
+        }
+        save_session(&app);
+        return Ok(());
+    }
+    tab_activated(&app, &tab_id, old_target_active);
+
+    // Lo que se veía en la destino queda detrás
+    for old in old_target_visible
+        .iter()
+        .filter_map(|id| app.get_webview(id))
+    {
+        let _ = old.hide();
+    }
+
+    if let Some(webview) = app.get_webview(&tab_id) {
+        if webview.reparent(&target).is_ok() {
+            let _ = webview.show();
+            layout_tabs(&target);
+        } else {
+            // Sin reparent: se cierra y se vuelve a crear en la destino con
+            // la misma etiqueta y su lista atrás/adelante
+            let engine_state = capture_engine_state(&app, &tab_id).await;
+            forget_shield_early(&app, &tab_id);
+            let _ = webview.close();
+            keep_engine_state(&tab_id, engine_state);
+            if label_freed(&app, &tab_id).await {
+                load_tab(&app, &tab_id, false)?;
+            } else {
+                // Queda descartada: se vuelve a crear al activarla
+                update_tab(&app, &tab_id, |tab| tab.discarded = true);
+            }
+        }
+    } else {
+        load_tab(&app, &tab_id, false)?;
+    }
+
+    if source_empty && source != MAIN_WINDOW {
+        if let Some(win) = app.get_window(&source) {
+            let _ = win.close();
+        }
+    } else if let Some(new_active) = new_active {
+        tab_activated(&app, &new_active, None);
+        if let Some(webview) = app.get_webview(&new_active) {
+            let _ = webview.show();
+        } else {
+            load_tab(&app, &new_active, false)?;
+        }
+    }
+
+    for label in &split_ended {
+        split_changed(&app, label);
+    }
+    // La pantalla completa de la pestaña movida no la sigue
+    for label in [&source, &window] {
+        apply_fullscreen(&app, label);
+    }
+    for label in [&source, &window] {
+        if let Some(tabs) = state
+            .lock()
+            .ok()
+            .and_then(|m| m.window(label).map(|w| w.tabs.clone()))
+        {
+            let _ = app.emit_to(label.as_str(), "tabs-changed", tabs);
+        }
+    }
+    save_session(&app);
+    Ok(())
+}
+
+#[tauri::command]
+fn move_tab(
+    app: tauri::AppHandle,
+    webview: tauri::Webview,
+    state: tauri::State<TabState>,
+    tab_id: String,
+    index: usize,
+) -> Result<(), String> {
+    require_ui(&webview)?;
+    state
+        .lock()
+        .map_err(|e| e.to_string())?
+        .window_of_mut(&tab_id)
+        .ok_or("Tab not found")?
+        .move_tab(&tab_id, index)?;
+    if let Some(label) = window_label_of(&app, &tab_id) {
+        tab_moved(&app, &tab_id, label);
+    }
+    save_session(&app);
+    Ok(())
+}
+
+#[tauri::command]
+fn pin_tab(
+    app: tauri::AppHandle,
+    webview: tauri::Webview,
+    state: tauri::State<TabState>,
+    tab_id: String,
+) -> Result<(), String> {
+    require_ui(&webview)?;
+    set_pinned(&app, &state, &tab_id, true)
+}
+
+#[tauri::command]
+fn unpin_tab(
+    app: tauri::AppHandle,
+    webview: tauri::Webview,
+    state: tauri::State<TabState>,
+    tab_id: String,
+) -> Result<(), String> {
+    require_ui(&webview)?;
+    set_pinned(&app, &state, &tab_id, false)
+}
+
+/// Cambia también el orden: la UI vuelve a pedir list_tabs al recibir
+/// el "tab-updated".
+fn set_pinned(
+    app: &tauri::AppHandle,
+    state: &TabState,
+    tab_id: &str,
+    pinned: bool,
+) -> Result<(), String> {
+    let (label, tab) = {
+        let mut manager = state.lock().map_err(|e| e.to_string())?;
+        let window = manager.window_of_mut(tab_id).ok_or("Tab not found")?;
+        if !window.set_pinned(tab_id, pinned)? {
+            return Ok(());
+        }
+        let tab = window.tabs.iter().find(|t| t.id == tab_id).cloned();
+        (window.label.clone(), tab)
+    };
+    if let Some(tab) = tab {
+        let _ = app.emit_to(label.as_str(), "tab-updated", tab);
+    }
+    tab_moved(app, tab_id, label);
+    save_session(app);
+    Ok(())
+}
+
+/// async: una pestaña restaurada crea aquí su webview, y hacerlo desde
+/// el hilo principal bloquea en Windows.
+#[tauri::command]
+async fn switch_tab(app: tauri::AppHandle, tab_id: String) -> Result<(), String> {
+    // Descartada o restaurada sin cargar: se crea ahora su webview
+    if app.get_webview(&tab_id).is_none() {
+        load_tab(&app, &tab_id, false)?;
+        save_session(&app);
+        return Ok(());
+    }
+
+    // Entre las dos mitades de la vista dividida solo cambia el foco
+    activate_tab(&app, &tab_id)?;
+    save_session(&app);
+    Ok(())
+}
+
+// --- VISTA DIVIDIDA ---
+
+/// La activa a un lado y `tab_id` al otro
+#[tauri::command]
+async fn enter_split_view(
+    app: tauri::AppHandle,
+    window: tauri::Window,
+    state: tauri::State<'_, TabState>,
+    tab_id: String,
+    orientation: Option<SplitOrientation>,
+) -> Result<(), String> {
+    if is_pip(&app, &tab_id) {
+        return_pip(&app, &tab_id)?;
+    }
+    let hidden = state
+        .lock()
+        .map_err(|e| e.to_string())?
+        .window_mut(window.label())
+        .ok_or("Window not found")?
+        .enter_split(&tab_id, orientation.unwrap_or(SplitOrientation::Horizontal))?;
+    for id in hidden {
+        if let Some(view) = app.get_webview(&id) {
+            let _ = view.hide();
+        }
+    }
+
+    // Descartada o sin cargar: se crea su webview sin quitar el foco
+    if app.get_webview(&tab_id).is_none() {
+        load_tab(&app, &tab_id, true)?;
+    }
+    if let Some(view) = app.get_webview(&tab_id) {
+        let _ = view.show();
+    }
+    split_changed(&app, window.label());
+    Ok(())
+}
+
+#[tauri::command]
+fn exit_split_view(
+    app: tauri::AppHandle,
+    window: tauri::Window,
+    state: tauri::State<TabState>,
+) -> Result<(), String> {
+    let hidden = state
+        .lock()
+        .map_err(|e| e.to_string())?
+        .window_mut(window.label())
+        .ok_or("Window not found")?
+        .exit_split()?;
+    for id in hidden {
+        if let Some(view) = app.get_webview(&id) {
+            let _ = view.hide();
+        }
+    }
+    split_changed(&app, window.label());
+    Ok(())
+}
+
+#[tauri::command]
+fn swap_split_panes(
+    app: tauri::AppHandle,
+    window: tauri::Window,
+    state: tauri::State<TabState>,
+) -> Result<(), String> {
+    state
+        .lock()
+        .map_err(|e| e.to_string())?
+        .window_mut(window.label())
+        .ok_or("Window not found")?
+        .swap_split()?;
+    split_changed(&app, window.label());
+    Ok(())
+}
+
+/// Arrastre del divisor: `ratio` es la parte que ocupa la primera mitad
+#[tauri::command]
+fn set_split_ratio(
+    app: tauri::AppHandle,
+    window: tauri::Window,
+    state: tauri::State<TabState>,
+    ratio: f64,
+) -> Result<(), String> {
+    state
+        .lock()
+        .map_err(|e| e.to_string())?
+        .window_mut(window.label())
+        .ok_or("Window not found")?
+        .set_split_ratio(ratio)?;
+    split_changed(&app, window.label());
+    Ok(())
+}
+
+// --- PANELES LATERALES ---
+
+/// La UI abre o cierra un panel a un lado: las pestañas dejan su hueco
+#[tauri::command]
+fn set_sidebars(
+    window: tauri::Window,
+    state: tauri::State<TabState>,
+    sidebars: Sidebars,
+) -> Result<(), String> {
+    state
+        .lock()
+        .map_err(|e| e.to_string())?
+        .window_mut(window.label())
+        .ok_or("Window not found")?
+        .sidebars = sidebars;
+    layout_tabs(&window);
+    Ok(())
+}
+
+// --- AJUSTES ---
+
+#[tauri::command]
+fn get_settings(settings: tauri::State<SettingsState>) -> Result<Settings, String> {
+    Ok(settings.lock().map_err(|e| e.to_string())?.get().clone())
+}
+
+#[tauri::command]
+fn set_settings(
+    webview: tauri::Webview,
+    store: tauri::State<SettingsState>,
+    settings: Settings,
+) -> Result<(), String> {
+    require_ui(&webview)?;
+    store.lock().map_err(|e| e.to_string())?.set(settings)
 }
 
-// ================================================================
-// INYECCIÓN DE RESPALDO — on_page_load
-// ================================================================
-// Red de seguridad para SPA navigations y recargas.
-// Los módulos usan IIFEs, así que la doble ejecución es segura.
-// ================================================================
+// --- CONTENEDORES ---
 
-fn inject_shield_fallback(webview: &tauri::Webview) {
-    for script in SHIELD_SCRIPTS {
-        let _ = webview.eval(*script);
-    }
+#[tauri::command]
+fn list_containers(
+    webview: tauri::Webview,
+    containers: tauri::State<ContainerState>,
+) -> Result<Vec<Container>, String> {
+    require_ui(&webview)?;
+    Ok(containers.lock().map_err(|e| e.to_string())?.list())
 }
 
-// ================================================================
-// COMANDOS TAURI
-// ================================================================
+#[tauri::command]
+fn create_container(
+    webview: tauri::Webview,
+    containers: tauri::State<ContainerState>,
+    name: String,
+    color: ContainerColor,
+) -> Result<Container, String> {
+    require_ui(&webview)?;
+    containers
+        .lock()
+        .map_err(|e| e.to_string())?
+        .create(name, color)
+}
 
+/// Nombre o color nuevos: las pestañas abiertas en él se actualizan
 #[tauri::command]
-async fn create_tab(
+fn update_container(
     app: tauri::AppHandle,
-    state: tauri::State<'_, TabState>,
-    url: Option<String>,
-) -> Result<String, String> {
-    let (tab_id, old_active) = {
-        let mut manager = state.lock().map_err(|e| e.to_string())?;
-        let id = manager.new_id();
-        let old = manager.active_tab.clone();
-        (id, old)
+    webview: tauri::Webview,
+    containers: tauri::State<ContainerState>,
+    state: tauri::State<TabState>,
+    id: String,
+    name: String,
+    color: ContainerColor,
+) -> Result<Container, String> {
+    require_ui(&webview)?;
+    let container = containers
+        .lock()
+        .map_err(|e| e.to_string())?
+        .update(&id, name, color)?;
+    let ids: Vec<String> = {
+        let manager = state.lock().map_err(|e| e.to_string())?;
+        manager
+            .tabs()
+            .filter(|t| t.container.as_ref().is_some_and(|c| c.id == id))
+            .map(|t| t.id.clone())
+            .collect()
     };
+    for tab_id in ids {
+        update_tab(&app, &tab_id, |tab| tab.container = Some(container.clone()));
+    }
+    Ok(container)
+}
 
-    if let Some(old_id) = old_active {
-        if let Some(webview) = app.get_webview(&old_id) {
-            let _ = webview.hide();
-        }
+/// Se borran también sus cookies y datos, así que no con pestañas abiertas
+#[tauri::command]
+fn remove_container(
+    webview: tauri::Webview,
+    containers: tauri::State<ContainerState>,
+    state: tauri::State<TabState>,
+    id: String,
+) -> Result<(), String> {
+    require_ui(&webview)?;
+    let in_use = state
+        .lock()
+        .map_err(|e| e.to_string())?
+        .tabs()
+        .any(|t| t.container.as_ref().is_some_and(|c| c.id == id));
+    if in_use {
+        return Err("Container has open tabs".to_string());
     }
+    containers.lock().map_err(|e| e.to_string())?.remove(&id)
+}
 
-    let win = app.get_window("main").ok_or("No main window")?;
+#[tauri::command]
+fn list_container_rules(
+    webview: tauri::Webview,
+    containers: tauri::State<ContainerState>,
+) -> Result<Vec<HostRule>, String> {
+    require_ui(&webview)?;
+    Ok(containers.lock().map_err(|e| e.to_string())?.rules())
+}
 
-    let load_tab_id = tab_id.clone();
+/// "Abrir siempre jira.example.com en Trabajo"
+#[tauri::command]
+fn set_container_rule(
+    webview: tauri::Webview,
+    containers: tauri::State<ContainerState>,
+    host: String,
+    container: String,
+) -> Result<(), String> {
+    require_ui(&webview)?;
+    containers
+        .lock()
+        .map_err(|e| e.to_string())?
+        .set_rule(&host, &container)
+}
 
-    let stored_url = url.clone().unwrap_or_else(|| "atom://home".to_string());
+#[tauri::command]
+fn remove_container_rule(
+    webview: tauri::Webview,
+    containers: tauri::State<ContainerState>,
+    host: String,
+) -> Result<(), String> {
+    require_ui(&webview)?;
+    containers
+        .lock()
+        .map_err(|e| e.to_string())?
+        .remove_rule(&host)
+}
 
-    let webview_url = match url {
-        Some(u) if !u.is_empty() => {
-            let parsed = u.parse().unwrap_or_else(|_| "about:blank".parse().unwrap());
-            WebviewUrl::External(parsed)
-        }
-        _ => WebviewUrl::App("home.html".into()),
-    };
+// --- SESIÓN ---
 
-    let size = win.inner_size().map_err(|e| e.to_string())?;
+/// Sesión de un cierre no limpio, a la espera de que el usuario decida
+#[tauri::command]
+fn get_pending_session(
+    webview: tauri::Webview,
+    sessions: tauri::State<SessionState>,
+) -> Result<Option<Session>, String> {
+    require_ui(&webview)?;
+    let sessions = sessions.lock().map_err(|e| e.to_string())?;
+    Ok(sessions.pending().cloned())
+}
 
-    let webview = win
-        .add_child(
-            tauri::webview::WebviewBuilder::new(&tab_id, webview_url)
-                .auto_resize()
-                .on_download(move |webview, event| match event {
-                    tauri::webview::DownloadEvent::Requested { url, destination } => {
-                        let filename = destination
-                            .file_name()
-                            .map(|f| f.to_string_lossy().to_string())
-                            .unwrap_or_default();
-                        let _ = webview.app_handle().emit(
-                            "download-started",
-                            serde_json::json!({
-                                "id": url.to_string(),
-                                "filename": filename,
-                                "path": destination.to_string_lossy()
-                            }),
-                        );
-                        true
-                    }
-                    tauri::webview::DownloadEvent::Finished { url, success, .. } => {
-                        let _ = webview.app_handle().emit(
-                            "download-finished",
-                            serde_json::json!({
-                                "id": url.to_string(),
-                                "success": success
-                            }),
-                        );
-                        true
-                    }
-                    _ => true,
-                })
-                .on_navigation(move |_url| {
-                    true
-                })
-                .on_page_load(move |webview, _payload| {
-                    // Respaldo: inyectar shield por si la inyección temprana falló
-                    // Esto cubre recargas y navegaciones SPA
-                    inject_shield_fallback(&webview);
+#[tauri::command]
+async fn restore_pending_session(
+    app: tauri::AppHandle,
+    webview: tauri::Webview,
+    sessions: tauri::State<'_, SessionState>,
+) -> Result<(), String> {
+    require_ui(&webview)?;
+    let session = sessions
+        .lock()
+        .map_err(|e| e.to_string())?
+        .take_pending()
+        .ok_or("No pending session")?;
+    restore_session(&app, session)
+}
 
-                    if let Ok(url) = webview.url() {
-                        let _ = webview.app_handle().emit(
-                            "url-changed",
-                            TabInfo {
-                                id: load_tab_id.clone(),
-                                url: url.to_string(),
-                            },
-                        );
-                    }
-                }),
-            tauri::LogicalPosition::new(0.0, BARRA_ALTURA),
-            tauri::LogicalSize::new(size.width as f64, size.height as f64 - BARRA_ALTURA),
-        )
-        .map_err(|e| e.to_string())?;
+#[tauri::command]
+fn discard_pending_session(
+    webview: tauri::Webview,
+    sessions: tauri::State<SessionState>,
+) -> Result<(), String> {
+    require_ui(&webview)?;
+    sessions.lock().map_err(|e| e.to_string())?.take_pending();
+    Ok(())
+}
 
-    // --- ORDEN CRÍTICO ---
-    // 1. Primero: inyección temprana del shield (antes de cualquier navegación)
-    inject_shield_early(&webview);
+#[tauri::command]
+async fn allow_popup(
+    app: tauri::AppHandle,
+    webview: tauri::Webview,
+    window: tauri::Window,
+    popups: tauri::State<'_, PopupState>,
+    popup_id: String,
+    always: bool,
+) -> Result<String, String> {
+    require_ui(&webview)?;
+    let popup = {
+        let mut blocker = popups.lock().map_err(|e| e.to_string())?;
+        let popup = blocker.take(&popup_id).ok_or("Popup not found")?;
+        if always {
+            blocker.allow_host(&popup.opener_host);
+        }
+        popup
+    };
+    // En la ventana del opener; si ya se cerró, en la que lo permite
+    let label = window_label_of(&app, &popup.tab_id).unwrap_or_else(|| window.label().to_string());
+    open_tab(
+        &app,
+        &label,
+        Some(popup.url),
+        false,
+        Some(popup.tab_id),
+        None,
+    )
+}
 
-    // 2. Segundo: bloqueador de red (intercepta peticiones HTTP)
-    #[cfg(target_os = "windows")]
-    crate::ad_blocker::network_blocker::setup_network_blocker(&webview);
+#[tauri::command]
+fn dismiss_popup(
+    webview: tauri::Webview,
+    popups: tauri::State<PopupState>,
+    popup_id: String,
+) -> Result<(), String> {
+    require_ui(&webview)?;
+    let mut blocker = popups.lock().map_err(|e| e.to_string())?;
+    blocker.take(&popup_id).ok_or("Popup not found")?;
+    Ok(())
+}
 
-    {
-        let mut manager = state.lock().map_err(|e| e.to_string())?;
-        manager.tabs.insert(tab_id.clone(), stored_url);
-        manager.active_tab = Some(tab_id.clone());
-    }
+// --- USER SCRIPTS ---
 
-    Ok(tab_id)
+#[tauri::command]
+fn list_user_scripts(
+    webview: tauri::Webview,
+    user_scripts: tauri::State<UserScriptState>,
+) -> Result<Vec<UserScriptInfo>, String> {
+    require_ui(&webview)?;
+    let user_scripts = user_scripts.lock().map_err(|e| e.to_string())?;
+    Ok(user_scripts.list())
 }
 
 #[tauri::command]
-fn close_tab(
+fn enable_user_script(
     app: tauri::AppHandle,
-    state: tauri::State<TabState>,
-    tab_id: String,
+    webview: tauri::Webview,
+    user_scripts: tauri::State<UserScriptState>,
+    id: String,
 ) -> Result<(), String> {
-    let mut manager = state.lock().map_err(|e| e.to_string())?;
+    require_ui(&webview)?;
+    user_scripts
+        .lock()
+        .map_err(|e| e.to_string())?
+        .set_enabled(&id, true)?;
+    refresh_shield_early(&app);
+    Ok(())
+}
 
-    if !manager.tabs.contains_key(&tab_id) {
-        return Err("Tab not found".to_string());
-    }
+#[tauri::command]
+fn disable_user_script(
+    app: tauri::AppHandle,
+    webview: tauri::Webview,
+    user_scripts: tauri::State<UserScriptState>,
+    id: String,
+) -> Result<(), String> {
+    require_ui(&webview)?;
+    user_scripts
+        .lock()
+        .map_err(|e| e.to_string())?
+        .set_enabled(&id, false)?;
+    refresh_shield_early(&app);
+    Ok(())
+}
 
-    if let Some(webview) = app.get_webview(&tab_id) {
-        let _ = webview.close();
-    }
+#[tauri::command]
+fn reload_user_scripts(
+    app: tauri::AppHandle,
+    webview: tauri::Webview,
+    user_scripts: tauri::State<UserScriptState>,
+) -> Result<Vec<UserScriptInfo>, String> {
+    require_ui(&webview)?;
+    let list = {
+        let mut user_scripts = user_scripts.lock().map_err(|e| e.to_string())?;
+        user_scripts.reload();
+        user_scripts.list()
+    };
+    refresh_shield_early(&app);
+    Ok(list)
+}
 
-    manager.tabs.remove(&tab_id);
+// --- USER STYLES ---
 
-    if manager.active_tab.as_ref() == Some(&tab_id) {
-        manager.active_tab = manager.tabs.keys().next().cloned();
-        if let Some(ref new_active) = manager.active_tab {
-            if let Some(webview) = app.get_webview(new_active) {
-                let _ = webview.show();
-            }
-        }
-    }
-    Ok(())
+#[tauri::command]
+fn list_user_styles(
+    user_styles: tauri::State<UserStyleState>,
+) -> Result<Vec<UserStyleInfo>, String> {
+    let user_styles = user_styles.lock().map_err(|e| e.to_string())?;
+    Ok(user_styles.list())
 }
 
+// --- DIAGNÓSTICO DEL SHIELD ---
+
+/// Lo invocan los propios módulos desde la página (ver shield.rs), así
+/// que es orientativo: ver diagnostics.rs.
 #[tauri::command]
-fn switch_tab(
-    app: tauri::AppHandle,
-    state: tauri::State<TabState>,
-    tab_id: String,
+fn shield_report(
+    webview: tauri::Webview,
+    tabs: tauri::State<TabState>,
+    diagnostics: tauri::State<DiagnosticsState>,
+    report: ShieldReport,
 ) -> Result<(), String> {
-    let old_active = {
-        let mut manager = state.lock().map_err(|e| e.to_string())?;
-        if !manager.tabs.contains_key(&tab_id) {
-            return Err("Tab not found".to_string());
-        }
-        let old = manager.active_tab.clone();
-        manager.active_tab = Some(tab_id.clone());
-        old
+    let tab_id = webview.label().to_string();
+    let tab_url = {
+        let manager = tabs.lock().map_err(|e| e.to_string())?;
+        manager.get(&tab_id).ok_or("Tab not found")?.url.clone()
     };
-    if let Some(old) = old_active {
-        if let Some(view) = app.get_webview(&old) {
-            let _ = view.hide();
-        }
-    }
-    if let Some(view) = app.get_webview(&tab_id) {
-        let _ = view.show();
+    if !report.is_plausible(&tab_url) {
+        return Err("Invalid report".to_string());
     }
+    let mut diagnostics = diagnostics.lock().map_err(|e| e.to_string())?;
+    diagnostics.record(&tab_id, report);
     Ok(())
 }
 
+/// Lo invoca FORM_WATCH_SCRIPT desde la página.
 #[tauri::command]
-fn navigate(app: tauri::AppHandle, state: tauri::State<TabState>, url: String) {
-    let manager = state.lock().unwrap();
-    if let Some(ref active) = manager.active_tab {
-        if let Some(webview) = app.get_webview(active) {
-            // Usar eval para navegar — el shield se re-inyecta vía on_page_load
-            // y la inyección temprana (AddScriptToExecuteOnDocumentCreated) ya está
-            // registrada y se ejecutará automáticamente en la nueva página
-            let script = format!("window.location.href = '{}'", url.replace('\'', "\\'"));
-            let _ = webview.eval(&script);
-        }
-    }
+fn tab_form_state(webview: tauri::Webview, dirty: bool) {
+    update_tab(webview.app_handle(), webview.label(), |tab| {
+        tab.form_dirty = dirty
+    });
 }
 
 #[tauri::command]
-fn go_back(app: tauri::AppHandle, state: tauri::State<TabState>) {
-    let manager = state.lock().unwrap();
-    if let Some(ref active) = manager.active_tab {
-        if let Some(webview) = app.get_webview(active) {
-            let _ = webview.eval("window.history.back()");
-        }
-    }
+fn get_discard_policy(discarder: tauri::State<DiscardState>) -> Result<DiscardPolicy, String> {
+    Ok(discarder.lock().map_err(|e| e.to_string())?.policy())
 }
 
 #[tauri::command]
-fn go_forward(app: tauri::AppHandle, state: tauri::State<TabState>) {
-    let manager = state.lock().unwrap();
-    if let Some(ref active) = manager.active_tab {
-        if let Some(webview) = app.get_webview(active) {
-            let _ = webview.eval("window.history.forward()");
-        }
-    }
+fn set_discard_policy(
+    webview: tauri::Webview,
+    discarder: tauri::State<DiscardState>,
+    policy: DiscardPolicy,
+) -> Result<(), String> {
+    require_ui(&webview)?;
+    discarder
+        .lock()
+        .map_err(|e| e.to_string())?
+        .set_policy(policy)
 }
 
 #[tauri::command]
-fn reload(app: tauri::AppHandle, state: tauri::State<TabState>) {
-    let manager = state.lock().unwrap();
-    if let Some(ref active) = manager.active_tab {
-        if let Some(webview) = app.get_webview(active) {
-            let _ = webview.eval("window.location.reload()");
+fn get_shield_diagnostics(
+    app: tauri::AppHandle,
+    webview: tauri::Webview,
+    diagnostics: tauri::State<DiagnosticsState>,
+    tab_id: String,
+) -> Result<TabDiagnostics, String> {
+    require_ui(&webview)?;
+    let url = tab_webview(&app, &tab_id)?
+        .url()
+        .map_err(|e| e.to_string())?;
+    let diagnostics = diagnostics.lock().map_err(|e| e.to_string())?;
+    Ok(diagnostics.summary(&tab_id, url.as_str()))
+}
+
+/// Webview de la pestaña; las descartadas no tienen
+fn tab_webview(app: &tauri::AppHandle, tab_id: &str) -> Result<tauri::Webview, String> {
+    // Por el modelo: con la etiqueta de una ventana saldría su barra
+    window_label_of(app, tab_id).ok_or("Tab not found")?;
+    app.get_webview(tab_id)
+        .ok_or_else(|| "Tab is not loaded".to_string())
+}
+
+/// Webview de la pestaña; si está descartada se crea antes (con su
+/// historial), en primer plano solo si es la activa de su ventana.
+fn loaded_webview(app: &tauri::AppHandle, tab_id: &str) -> Result<tauri::Webview, String> {
+    if app.get_webview(tab_id).is_none() {
+        let background = {
+            let state = app.state::<TabState>();
+            let manager = state.lock().map_err(|e| e.to_string())?;
+            let window = manager.window_of(tab_id).ok_or("Tab not found")?;
+            window.active_tab.as_deref() != Some(tab_id)
+        };
+        load_tab(app, tab_id, background)?;
+    }
+    tab_webview(app, tab_id)
+}
+
+/// Solo lo que se puede abrir en una pestaña: nada de javascript: ni
+/// similares. "atom://home" es la home, servida junto a la barra. Los
+/// file: solo si los ha escrito el usuario en la barra de direcciones.
+fn navigation_url(
+    app: &tauri::AppHandle,
+    tab_id: &str,
+    url: &str,
+    allow_file: bool,
+) -> Result<tauri::Url, String> {
+    let url = tauri::Url::parse(url).map_err(|e| e.to_string())?;
+    match url.scheme() {
+        "http" | "https" => Ok(url),
+        "file" if allow_file => Ok(url),
+        "about" if url.path() == "blank" => Ok(url),
+        "atom" => {
+            let label = window_label_of(app, tab_id).ok_or("Tab not found")?;
+            let ui = app.get_webview(&label).ok_or("Window not found")?;
+            ui.url()
+                .map_err(|e| e.to_string())?
+                .join("home.html")
+                .map_err(|e| e.to_string())
         }
+        scheme => Err(format!("Cannot navigate to {scheme}: URLs")),
     }
 }
 
+/// Con la API del webview, no con eval: funciona aunque la CSP de la
+/// página no deje evaluar y en las páginas de error. La inyección temprana
+/// del shield sigue registrada y se aplica a la página nueva. Una
+/// descartada recupera antes su webview y su historial. async por eso
+/// mismo: crear el webview desde el hilo principal bloquea en Windows.
 #[tauri::command]
-async fn set_fullscreen(
+async fn navigate(
     app: tauri::AppHandle,
-    state: tauri::State<'_, TabState>,
-    is_fullscreen: bool,
+    webview: tauri::Webview,
+    tab_id: String,
+    url: String,
+    from_address_bar: Option<bool>,
 ) -> Result<(), String> {
-    let win = app.get_window("main").ok_or("No main window")?;
+    require_ui(&webview)?;
+    let url = navigation_url(&app, &tab_id, &url, from_address_bar.unwrap_or(false))?;
+    loaded_webview(&app, &tab_id)?
+        .navigate(url)
+        .map_err(|e| e.to_string())
+}
 
-    if is_fullscreen {
-        {
-            let mut manager = state.lock().map_err(|e| e.to_string())?;
-            if let (Ok(pos), Ok(size)) = (win.outer_position(), win.outer_size()) {
-                manager.pre_fs_pos = Some((pos.x, pos.y));
-                manager.pre_fs_size = Some((size.width, size.height));
+/// Un paso atrás o adelante en la lista del motor
+#[cfg(target_os = "windows")]
+fn step_history(webview: &tauri::Webview, forward: bool) -> Result<(), String> {
+    webview
+        .with_webview(move |wv| unsafe {
+            if let Ok(core) = wv.controller().CoreWebView2() {
+                let _ = if forward {
+                    core.GoForward()
+                } else {
+                    core.GoBack()
+                };
             }
-            manager.is_fullscreen = true;
-        }
-
-        let _ = app.emit_to("main", "fullscreen-change", true);
-        std::thread::sleep(std::time::Duration::from_millis(300));
+        })
+        .map_err(|e| e.to_string())
+}
 
-        if let Ok(Some(monitor)) = win.current_monitor() {
-            let m_pos = monitor.position();
-            let m_size = monitor.size();
-            let _ = win.set_always_on_top(true);
-            let _ = win.set_position(tauri::PhysicalPosition::new(m_pos.x, m_pos.y));
-            let _ = win.set_size(tauri::PhysicalSize::new(m_size.width, m_size.height));
-        }
+#[cfg(target_os = "linux")]
+fn step_history(webview: &tauri::Webview, forward: bool) -> Result<(), String> {
+    use webkit2gtk::WebViewExt;
 
-        std::thread::sleep(std::time::Duration::from_millis(50));
-        if let Ok(size) = win.inner_size() {
-            let manager = state.lock().map_err(|e| e.to_string())?;
-            let w = size.width as f64;
-            let h = size.height as f64;
-            for (id, _) in manager.tabs.iter() {
-                if let Some(wv) = win.get_webview(id) {
-                    let _ = wv.set_bounds(tauri::Rect {
-                        position: tauri::Position::Logical(tauri::LogicalPosition {
-                            x: 0.0,
-                            y: 0.0,
-                        }),
-                        size: tauri::Size::Logical(tauri::LogicalSize {
-                            width: w,
-                            height: h,
-                        }),
-                    });
-                }
+    webview
+        .with_webview(move |wv| {
+            let view = wv.inner();
+            if forward {
+                view.go_forward();
+            } else {
+                view.go_back();
             }
-        }
-    } else {
-        let _ = win.set_always_on_top(false);
+        })
+        .map_err(|e| e.to_string())
+}
 
-        let (prev_pos, prev_size) = {
-            let mut manager = state.lock().map_err(|e| e.to_string())?;
-            manager.is_fullscreen = false;
-            (manager.pre_fs_pos.take(), manager.pre_fs_size.take())
-        };
+/// Sin acceso al motor: lo único que queda es el history de la página
+#[cfg(not(any(target_os = "windows", target_os = "linux")))]
+fn step_history(webview: &tauri::Webview, forward: bool) -> Result<(), String> {
+    let script = if forward {
+        "window.history.forward()"
+    } else {
+        "window.history.back()"
+    };
+    webview.eval(script).map_err(|e| e.to_string())
+}
 
-        if let Some((x, y)) = prev_pos {
-            let _ = win.set_position(tauri::PhysicalPosition::new(x, y));
-        }
-        if let Some((w, h)) = prev_size {
-            let _ = win.set_size(tauri::PhysicalSize::new(w, h));
+/// can_go_back/can_go_forward los mantiene al día el propio motor
+fn go_in_history(app: &tauri::AppHandle, tab_id: &str, forward: bool) -> Result<(), String> {
+    let possible = {
+        let state = app.state::<TabState>();
+        let manager = state.lock().map_err(|e| e.to_string())?;
+        let tab = manager.get(tab_id).ok_or("Tab not found")?;
+        if forward {
+            tab.can_go_forward
+        } else {
+            tab.can_go_back
         }
+    };
+    if !possible {
+        return Err(if forward {
+            "Cannot go forward".to_string()
+        } else {
+            "Cannot go back".to_string()
+        });
+    }
+    step_history(&loaded_webview(app, tab_id)?, forward)
+}
 
-        std::thread::sleep(std::time::Duration::from_millis(50));
-        if let Ok(size) = win.inner_size() {
-            let manager = state.lock().map_err(|e| e.to_string())?;
-            let w = size.width as f64;
-            let h = size.height as f64;
-            let vh = if h > BARRA_ALTURA {
-                h - BARRA_ALTURA
-            } else {
-                0.0
-            };
-            for (id, _) in manager.tabs.iter() {
-                if let Some(wv) = win.get_webview(id) {
-                    let _ = wv.set_bounds(tauri::Rect {
-                        position: tauri::Position::Logical(tauri::LogicalPosition {
-                            x: 0.0,
-                            y: BARRA_ALTURA,
-                        }),
-                        size: tauri::Size::Logical(tauri::LogicalSize {
-                            width: w,
-                            height: vh,
-                        }),
-                    });
-                }
-            }
-        }
+#[tauri::command]
+async fn go_back(
+    app: tauri::AppHandle,
+    webview: tauri::Webview,
+    tab_id: String,
+) -> Result<(), String> {
+    require_ui(&webview)?;
+    go_in_history(&app, &tab_id, false)
+}
+
+#[tauri::command]
+async fn go_forward(
+    app: tauri::AppHandle,
+    webview: tauri::Webview,
+    tab_id: String,
+) -> Result<(), String> {
+    require_ui(&webview)?;
+    go_in_history(&app, &tab_id, true)
+}
 
-        let _ = app.emit_to("main", "fullscreen-change", false);
+/// Una descartada ya recarga al recuperar su webview
+#[tauri::command]
+async fn reload(
+    app: tauri::AppHandle,
+    webview: tauri::Webview,
+    tab_id: String,
+) -> Result<(), String> {
+    require_ui(&webview)?;
+    if app.get_webview(&tab_id).is_none() {
+        loaded_webview(&app, &tab_id)?;
+        return Ok(());
     }
+    tab_webview(&app, &tab_id)?
+        .reload()
+        .map_err(|e| e.to_string())
+}
 
+/// F11. La UI oculta su barra con "fullscreen-change".
+#[tauri::command]
+fn set_fullscreen(
+    app: tauri::AppHandle,
+    window: tauri::Window,
+    state: tauri::State<TabState>,
+    is_fullscreen: bool,
+) -> Result<(), String> {
+    let label = window.label();
+    state
+        .lock()
+        .map_err(|e| e.to_string())?
+        .window_mut(label)
+        .ok_or("Window not found")?
+        .is_fullscreen = is_fullscreen;
+    apply_fullscreen(&app, label);
+    let _ = app.emit_to(label, "fullscreen-change", is_fullscreen);
     Ok(())
 }
 
 #[tauri::command]
-fn get_active_tab(state: tauri::State<TabState>) -> Option<String> {
+fn get_active_tab(window: tauri::Window, state: tauri::State<TabState>) -> Option<String> {
     let manager = state.lock().unwrap();
-    manager.active_tab.clone()
+    manager.window(window.label())?.active_tab.clone()
 }
 
+/// Con la vista dividida, las dos mitades
 #[tauri::command]
-fn hide_active_tab(app: tauri::AppHandle, state: tauri::State<TabState>) {
-    let manager = state.lock().unwrap();
-    if let Some(ref active) = manager.active_tab {
-        if let Some(webview) = app.get_webview(active) {
-            let _ = webview.hide();
-        }
+fn hide_active_tab(app: tauri::AppHandle, window: tauri::Window) {
+    for webview in visible_webviews(&app, window.label()) {
+        let _ = webview.hide();
     }
 }
 
 #[tauri::command]
-fn show_active_tab(app: tauri::AppHandle, state: tauri::State<TabState>) {
-    let manager = state.lock().unwrap();
-    if let Some(ref active) = manager.active_tab {
-        if let Some(webview) = app.get_webview(active) {
-            let _ = webview.show();
-        }
+fn show_active_tab(app: tauri::AppHandle, window: tauri::Window) {
+    for webview in visible_webviews(&app, window.label()) {
+        let _ = webview.show();
     }
 }
 
 // --- CONTROLES DE VENTANA ---
 
 #[tauri::command]
-fn close_window(app: tauri::AppHandle) {
-    if let Some(window) = app.get_window("main") {
-        let _ = window.close();
-    }
+fn close_window(window: tauri::Window) {
+    let _ = window.close();
 }
 
 #[tauri::command]
-fn minimize_window(app: tauri::AppHandle) {
-    if let Some(window) = app.get_window("main") {
-        let _ = window.minimize();
-    }
+fn minimize_window(window: tauri::Window) {
+    let _ = window.minimize();
 }
 
 #[tauri::command]
-fn maximize_window(app: tauri::AppHandle) {
-    if let Some(window) = app.get_window("main") {
-        if let Ok(is_maximized) = window.is_maximized() {
-            if is_maximized {
-                let _ = window.unmaximize();
-            } else {
-                let _ = window.maximize();
-            }
+fn maximize_window(window: tauri::Window) {
+    if let Ok(is_maximized) = window.is_maximized() {
+        if is_maximized {
+            let _ = window.unmaximize();
+        } else {
+            let _ = window.maximize();
         }
     }
 }
@@ -482,9 +3600,13 @@ fn maximize_window(app: tauri::AppHandle) {
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     let tab_state: TabState = Arc::new(Mutex::new(TabManager::new()));
+    let popup_state: PopupState = Arc::new(Mutex::new(PopupBlocker::new()));
+    let diagnostics_state: DiagnosticsState = Arc::new(Mutex::new(ShieldDiagnostics::new()));
 
     tauri::Builder::default()
         .manage(tab_state.clone())
+        .manage(popup_state)
+        .manage(diagnostics_state)
         .invoke_handler(tauri::generate_handler![
             navigate,
             go_back,
@@ -492,7 +3614,58 @@ pub fn run() {
             go_forward,
             create_tab,
             close_tab,
+            reopen_closed_tab,
+            duplicate_tab,
+            list_recently_closed,
+            quick_switch,
+            list_history,
+            remove_history_entry,
+            clear_history,
+            list_bookmarks,
+            add_bookmark,
+            remove_bookmark,
+            get_favicon,
+            favicon_fetched,
+            list_tabs,
+            list_windows,
+            new_window,
+            is_private_window,
+            move_tab_to_window,
+            move_tab,
+            pin_tab,
+            unpin_tab,
             switch_tab,
+            enter_split_view,
+            exit_split_view,
+            swap_split_panes,
+            set_split_ratio,
+            pop_out_tab,
+            dock_tab,
+            set_sidebars,
+            get_settings,
+            set_settings,
+            list_containers,
+            create_container,
+            update_container,
+            remove_container,
+            list_container_rules,
+            set_container_rule,
+            remove_container_rule,
+            get_pending_session,
+            restore_pending_session,
+            discard_pending_session,
+            allow_popup,
+            dismiss_popup,
+            list_user_scripts,
+            enable_user_script,
+            disable_user_script,
+            reload_user_scripts,
+            list_user_styles,
+            shield_report,
+            get_shield_diagnostics,
+            tab_form_state,
+            get_discard_policy,
+            set_discard_policy,
             get_active_tab,
             set_fullscreen,
             hide_active_tab,
@@ -501,44 +3674,136 @@ pub fn run() {
             minimize_window,
             maximize_window
         ])
-        .setup(|_app| {
+        .setup(|app| {
+            let profile = app.path().app_data_dir()?;
+            // Restos de navegación privada de una ejecución que no cerró bien
+            let _ = private::wipe(&private::data_dir(&profile));
+            let user_scripts: UserScriptState =
+                Arc::new(Mutex::new(UserScripts::load(profile.join("userscripts"))));
+            app.manage(user_scripts);
+
+            let user_styles = UserStyles::load(profile.join("userstyles"));
+            let styles_dir = user_styles.dir();
+            let user_styles: UserStyleState = Arc::new(Mutex::new(user_styles));
+            app.manage(user_styles);
+
+            // Los cambios en userstyles se aplican en caliente
+            let handle = app.handle().clone();
+            watcher::watch_dir(styles_dir, move || refresh_user_styles(&handle));
+
+            // En debug los módulos del shield se leen de disco y se recargan
+            // al guardar, sin recompilar (ver shield.rs)
+            #[cfg(debug_assertions)]
+            {
+                let shield_dir = shield::dev_dir();
+                shield::load_dev_sources(&shield_dir);
+                let handle = app.handle().clone();
+                let watched = shield_dir.clone();
+                watcher::watch_dir(watched, move || {
+                    shield::load_dev_sources(&shield_dir);
+                    refresh_shield_early(&handle);
+                });
+            }
+
+            let discarder: DiscardState = Arc::new(Mutex::new(Discarder::load(profile.clone())));
+            app.manage(discarder);
+            let handle = app.handle().clone();
+            std::thread::spawn(move || loop {
+                std::thread::sleep(discard::CHECK_INTERVAL);
+                discard_idle_tabs(&handle);
+            });
+
+            let history: HistoryState = Arc::new(Mutex::new(History::load(profile.clone())));
+            app.manage(history);
+            let handle = app.handle().clone();
+            std::thread::spawn(move || loop {
+                std::thread::sleep(history::SAVE_INTERVAL);
+                save_history(&handle);
+            });
+            let favicons: FaviconState = Arc::new(Mutex::new(Favicons::load(profile.clone())));
+            app.manage(favicons);
+            let bookmarks: BookmarkState = Arc::new(Mutex::new(Bookmarks::load(profile.clone())));
+            app.manage(bookmarks);
+
+            // Antes que la sesión: sus pestañas vuelven a su contenedor
+            let containers: ContainerState =
+                Arc::new(Mutex::new(Containers::load(profile.clone())));
+            app.manage(containers);
+
+            // Sesión anterior: se restaura sola si se cerró bien; si no,
+            // la UI pregunta (get_pending_session / restore_pending_session)
+            let settings = SettingsStore::load(profile.clone());
+            let restore_all = settings.get().restore_session;
+            let settings: SettingsState = Arc::new(Mutex::new(settings));
+            app.manage(settings);
+
+            // Las fijadas vuelven siempre, aunque no se restaure la sesión
+            // o el cierre no fuera limpio
+            let (mut sessions, previous) = SessionStore::open(profile);
+            let startup = match previous {
+                Some(session) if restore_all => Some(session),
+                Some(session) => Some(session.split_pinned().0).filter(|s| !s.is_empty()),
+                None => sessions.take_pending_pinned(),
+            };
+            // Lo que quede pendiente lo pregunta la UI (get_pending_session)
+            let sessions: SessionState = Arc::new(Mutex::new(sessions));
+            app.manage(sessions);
+            if let Some(session) = startup {
+                let handle = app.handle().clone();
+                tauri::async_runtime::spawn(async move {
+                    let _ = restore_session(&handle, session);
+                });
+            }
             Ok(())
         })
         .on_window_event(|window, event| {
-            if let tauri::WindowEvent::Resized(size) = event {
-                if window.label() == "main" {
-                    let tab_state: TabState = window.state::<TabState>().inner().clone();
-                    let manager = tab_state.lock().unwrap();
-                    let is_full = manager.is_fullscreen;
-
-                    for (id, _) in manager.tabs.iter() {
-                        if let Some(webview) = window.get_webview(id) {
-                            let width = size.width as f64;
-                            let height = size.height as f64;
-
-                            let top_offset = if is_full { 0.0 } else { BARRA_ALTURA };
-                            let view_height = if height > top_offset {
-                                height - top_offset
-                            } else {
-                                0.0
-                            };
-
-                            let _ = webview.set_bounds(tauri::Rect {
-                                position: tauri::Position::Logical(tauri::LogicalPosition {
-                                    x: 0.0,
-                                    y: top_offset,
-                                }),
-                                size: tauri::Size::Logical(tauri::LogicalSize {
-                                    width,
-                                    height: view_height,
-                                }),
-                            });
-                        }
+            let app = window.app_handle();
+            // Ventana flotante: cerrarla acopla la pestaña, no la cierra
+            if let Some(tab_id) = pip_tab_of(window.label()) {
+                match event {
+                    tauri::WindowEvent::Resized(_)
+                    | tauri::WindowEvent::ScaleFactorChanged { .. } => layout_pip(window, tab_id),
+                    tauri::WindowEvent::CloseRequested { api, .. } => {
+                        api.prevent_close();
+                        let (app, tab_id) = (app.clone(), tab_id.to_string());
+                        tauri::async_runtime::spawn(async move {
+                            let _ = dock_tab(app, tab_id).await;
+                        });
+                    }
+                    _ => {}
+                }
+                return;
+            }
+            let (registered, last) = match window.state::<TabState>().lock() {
+                Ok(manager) => (
+                    manager.window(window.label()).is_some(),
+                    manager.windows.len() == 1,
+                ),
+                Err(_) => return,
+            };
+            if !registered {
+                return;
+            }
+            match event {
+                tauri::WindowEvent::Moved(_) => save_session(app),
+                tauri::WindowEvent::Resized(_) => {
+                    layout_tabs(window);
+                    save_session(app);
+                }
+                tauri::WindowEvent::ScaleFactorChanged { .. } => layout_tabs(window),
+                // La sesión se cierra con la última ventana; las demás
+                // simplemente desaparecen de ella
+                tauri::WindowEvent::CloseRequested { .. } if last => close_session(app),
+                tauri::WindowEvent::Destroyed => {
+                    close_pip_windows(app, window.label());
+                    if !last {
+                        forget_window(app, window.label());
                     }
                 }
+                _ => {}
             }
         })
         .plugin(tauri_plugin_shell::init())
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
-}
\ No newline at end of file
+}
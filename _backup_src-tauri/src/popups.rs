@@ -0,0 +1,110 @@
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+
+use crate::ad_blocker::popup_blocker::is_popup_domain;
+
+// ================================================================
+// VENTANAS NUEVAS — window.open / target="_blank"
+// ================================================================
+// Toda petición de ventana nueva se convierte en una pestaña de Atom.
+// Sin gesto del usuario (o hacia POPUP_DOMAINS) se bloquea y la UI
+// recibe "popup-blocked" para ofrecer "permitir una vez / siempre".
+// ================================================================
+
+#[derive(Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BlockReason {
+    NoUserGesture,
+    PopupDomain,
+}
+
+pub enum PopupDecision {
+    Open { background: bool },
+    Block(BlockReason),
+}
+
+#[derive(Clone, Serialize)]
+pub struct BlockedPopup {
+    pub id: String,
+    pub tab_id: String,
+    pub url: String,
+    pub opener_host: String,
+    pub reason: BlockReason,
+}
+
+pub struct PopupBlocker {
+    /// Hosts con "permitir siempre": sus popups sin gesto se abren igualmente
+    allowed_hosts: HashSet<String>,
+    blocked: HashMap<String, BlockedPopup>,
+    counter: u32,
+}
+
+impl PopupBlocker {
+    pub fn new() -> Self {
+        Self {
+            allowed_hosts: HashSet::new(),
+            blocked: HashMap::new(),
+            counter: 0,
+        }
+    }
+
+    pub fn decide(
+        &self,
+        opener_host: &str,
+        url: &str,
+        user_gesture: bool,
+        background: bool,
+    ) -> PopupDecision {
+        if is_popup_domain(url) {
+            return PopupDecision::Block(BlockReason::PopupDomain);
+        }
+        if !user_gesture && !self.allowed_hosts.contains(opener_host) {
+            return PopupDecision::Block(BlockReason::NoUserGesture);
+        }
+        PopupDecision::Open { background }
+    }
+
+    pub fn record(
+        &mut self,
+        tab_id: &str,
+        url: &str,
+        opener_host: &str,
+        reason: BlockReason,
+    ) -> BlockedPopup {
+        self.counter += 1;
+        let popup = BlockedPopup {
+            id: format!("popup-{}", self.counter),
+            tab_id: tab_id.to_string(),
+            url: url.to_string(),
+            opener_host: opener_host.to_string(),
+            reason,
+        };
+        self.blocked.insert(popup.id.clone(), popup.clone());
+        popup
+    }
+
+    pub fn take(&mut self, popup_id: &str) -> Option<BlockedPopup> {
+        self.blocked.remove(popup_id)
+    }
+
+    pub fn allow_host(&mut self, host: &str) {
+        if !host.is_empty() {
+            self.allowed_hosts.insert(host.to_string());
+        }
+    }
+
+    /// Los popups pendientes de una pestaña cerrada ya no tienen sentido
+    pub fn forget_tab(&mut self, tab_id: &str) {
+        self.blocked.retain(|_, p| p.tab_id != tab_id);
+    }
+}
+
+pub type PopupState = Arc<Mutex<PopupBlocker>>;
+
+pub fn host_of(url: &str) -> String {
+    tauri::Url::parse(url)
+        .ok()
+        .and_then(|u| u.host_str().map(|h| h.to_lowercase()))
+        .unwrap_or_default()
+}
@@ -0,0 +1,30 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+// ================================================================
+// NAVEGACIÓN PRIVADA — <perfil>/private
+// ================================================================
+// Las pestañas de las ventanas privadas guardan cookies, caché y
+// localStorage aquí, aparte del perfil. El directorio se borra al
+// cerrar la última pestaña privada y al arrancar, por si la ejecución
+// anterior no terminó bien.
+// ================================================================
+
+const PRIVATE_DIR: &str = "private";
+/// El motor suelta sus ficheros un rato después de cerrar el webview
+pub const WIPE_ATTEMPTS: u32 = 10;
+pub const WIPE_RETRY: Duration = Duration::from_millis(500);
+
+pub fn data_dir(profile: &Path) -> PathBuf {
+    profile.join(PRIVATE_DIR)
+}
+
+/// Borra el directorio entero; si ya no existe, no hay nada que hacer.
+pub fn wipe(dir: &Path) -> io::Result<()> {
+    match fs::remove_dir_all(dir) {
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+        result => result,
+    }
+}
@@ -0,0 +1,367 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::tabs::Tab;
+
+// ================================================================
+// SESIÓN — <perfil>/session.json
+// ================================================================
+// Se reescribe entera desde un hilo propio: DEBOUNCE después del último
+// cambio (como mucho MAX_DELAY después del primero) y de forma atómica,
+// fichero temporal + rename.
+//
+// session.lock existe mientras Atom está abierto. Si al arrancar sigue
+// ahí, el cierre anterior no fue limpio: en vez de restaurar sin más se
+// pregunta al usuario. Mientras decide, esa sesión se aparta a
+// session.crashed.json para que los guardados de esta no la pisen.
+// ================================================================
+
+const SESSION_FILE: &str = "session.json";
+const CRASHED_FILE: &str = "session.crashed.json";
+const LOCK_FILE: &str = "session.lock";
+const DEBOUNCE: Duration = Duration::from_millis(1000);
+const MAX_DELAY: Duration = Duration::from_secs(5);
+/// Pestañas cerradas que se recuerdan para reabrirlas
+const MAX_CLOSED: usize = 25;
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SessionTab {
+    pub url: String,
+    #[serde(default)]
+    pub title: String,
+    #[serde(default)]
+    pub pinned: bool,
+    /// Id del contenedor; si ya no existe se abre fuera de él
+    #[serde(default)]
+    pub container: Option<String>,
+    /// Entradas de atrás/adelante, incluida la actual
+    #[serde(default)]
+    pub history: Vec<String>,
+    #[serde(default)]
+    pub history_index: usize,
+}
+
+impl SessionTab {
+    pub fn from_tab(tab: &Tab) -> Self {
+        Self {
+            url: tab.url.clone(),
+            title: tab.title.clone(),
+            pinned: tab.pinned,
+            container: tab.container.as_ref().map(|c| c.id.clone()),
+            history: tab.history.clone(),
+            history_index: tab.history_index,
+        }
+    }
+
+    /// Pestaña sin cargar: el webview se crea al activarla. El contenedor
+    /// lo resuelve quien la restaura, que tiene la lista.
+    pub fn into_tab(self, id: String) -> Tab {
+        let mut tab = Tab::new(id, self.url, None);
+        tab.loading = false;
+        tab.discarded = true;
+        tab.title = self.title;
+        tab.pinned = self.pinned;
+        if self.history.get(self.history_index) == Some(&tab.url) {
+            tab.history = self.history;
+            tab.history_index = self.history_index;
+        }
+        tab
+    }
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub struct WindowGeometry {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    pub maximized: bool,
+}
+
+/// Pestaña cerrada, con el sitio que ocupaba en la barra
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ClosedTab {
+    #[serde(flatten)]
+    pub tab: SessionTab,
+    pub index: usize,
+    /// Lista atrás/adelante del motor al cerrarla (ver engine_state en
+    /// lib.rs). Solo en memoria: tras reiniciar queda `tab.history`.
+    #[serde(skip)]
+    pub engine_state: Option<Vec<u8>>,
+}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct SessionWindow {
+    /// Solo para recordar la geometría mientras está minimizada
+    #[serde(skip)]
+    pub label: String,
+    pub tabs: Vec<SessionTab>,
+    /// Índice en `tabs` de la pestaña activa
+    pub active: Option<usize>,
+    pub geometry: Option<WindowGeometry>,
+}
+
+impl SessionWindow {
+    /// Separa las fijadas, que se restauran siempre, del resto.
+    fn split_pinned(self) -> (SessionWindow, SessionWindow) {
+        let empty = || SessionWindow {
+            label: self.label.clone(),
+            geometry: self.geometry,
+            ..Default::default()
+        };
+        let (mut pinned, mut rest) = (empty(), empty());
+        for (i, tab) in self.tabs.into_iter().enumerate() {
+            let target = if tab.pinned { &mut pinned } else { &mut rest };
+            if self.active == Some(i) {
+                target.active = Some(target.tabs.len());
+            }
+            target.tabs.push(tab);
+        }
+        (pinned, rest)
+    }
+}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct Session {
+    /// La primera es la ventana principal
+    pub windows: Vec<SessionWindow>,
+    /// Cerradas recientemente, la última al final. Las lleva el propio
+    /// SessionStore: lo que venga aquí al guardar se ignora.
+    #[serde(default)]
+    pub closed: Vec<ClosedTab>,
+}
+
+impl Session {
+    pub fn is_empty(&self) -> bool {
+        self.windows.iter().all(|w| w.tabs.is_empty())
+    }
+
+    /// Separa las fijadas del resto, ventana por ventana. Las ventanas
+    /// que se quedan sin pestañas desaparecen de cada mitad.
+    pub fn split_pinned(self) -> (Session, Session) {
+        let (mut pinned, mut rest) = (Session::default(), Session::default());
+        for window in self.windows {
+            let (p, r) = window.split_pinned();
+            if !p.tabs.is_empty() {
+                pinned.windows.push(p);
+            }
+            if !r.tabs.is_empty() {
+                rest.windows.push(r);
+            }
+        }
+        (pinned, rest)
+    }
+}
+
+enum Message {
+    Save(Session),
+    /// Escribir ya y avisar al terminar
+    Flush(Session, mpsc::Sender<()>),
+}
+
+pub struct SessionStore {
+    dir: PathBuf,
+    writer: mpsc::Sender<Message>,
+    /// Sesión de un cierre no limpio, a la espera de que el usuario decida
+    pending: Option<Session>,
+    /// Última geometría conocida de cada ventana, para cuando está minimizada
+    geometry: HashMap<String, WindowGeometry>,
+    closed: Vec<ClosedTab>,
+    /// Última sesión guardada, para cerrar con ella
+    last: Option<Session>,
+}
+
+impl SessionStore {
+    /// Lee la sesión anterior y marca esta como abierta. Devuelve además
+    /// la sesión a restaurar directamente, si el cierre anterior fue limpio.
+    pub fn open(dir: PathBuf) -> (Self, Option<Session>) {
+        let _ = fs::create_dir_all(&dir);
+        let crashed = dir.join(LOCK_FILE).exists();
+        let mut previous = fs::read_to_string(dir.join(SESSION_FILE))
+            .ok()
+            .and_then(|s| serde_json::from_str::<Session>(&s).ok());
+        // Las cerradas se recuperan siempre, se restaure o no la sesión
+        let closed = previous
+            .as_mut()
+            .map(|s| std::mem::take(&mut s.closed))
+            .unwrap_or_default();
+        let previous = previous.filter(|s| !s.is_empty());
+        let _ = fs::write(dir.join(LOCK_FILE), std::process::id().to_string());
+
+        let (writer, messages) = mpsc::channel();
+        let writer_dir = dir.clone();
+        std::thread::spawn(move || write_loop(&writer_dir, messages));
+
+        let crashed_file = dir.join(CRASHED_FILE);
+        let (restore, pending) = if crashed {
+            // Si la sesión caída no trae nada, sigue valiendo la apartada
+            // de una caída anterior sin decidir
+            if previous.is_some() {
+                let _ = fs::rename(dir.join(SESSION_FILE), &crashed_file);
+            }
+            let pending = fs::read_to_string(&crashed_file)
+                .ok()
+                .and_then(|s| serde_json::from_str::<Session>(&s).ok())
+                .map(|mut s| {
+                    s.closed.clear();
+                    s
+                })
+                .filter(|s| !s.is_empty());
+            (None, pending)
+        } else {
+            // Se cerró bien sin que el usuario decidiera: ya no se ofrece
+            let _ = fs::remove_file(&crashed_file);
+            (previous, None)
+        };
+        (
+            Self {
+                dir,
+                writer,
+                pending,
+                geometry: HashMap::new(),
+                closed,
+                last: None,
+            },
+            restore,
+        )
+    }
+
+    pub fn save(&mut self, session: Session) {
+        self.last = Some(session.clone());
+        let session = self.complete(session);
+        let _ = self.writer.send(Message::Save(session));
+    }
+
+    /// Añade lo que lleva el propio store: la geometría de las ventanas
+    /// minimizadas y las pestañas cerradas.
+    fn complete(&mut self, mut session: Session) -> Session {
+        for window in session.windows.iter_mut() {
+            match window.geometry {
+                Some(geometry) => {
+                    self.geometry.insert(window.label.clone(), geometry);
+                }
+                None => window.geometry = self.geometry.get(&window.label).copied(),
+            }
+        }
+        session.closed = self.closed.clone();
+        session
+    }
+
+    pub fn push_closed(&mut self, closed: ClosedTab) {
+        self.closed.push(closed);
+        if self.closed.len() > MAX_CLOSED {
+            self.closed.remove(0);
+        }
+    }
+
+    /// `index` como en closed(): 0 es la más reciente
+    pub fn take_closed(&mut self, index: usize) -> Option<ClosedTab> {
+        let i = self.closed.len().checked_sub(index + 1)?;
+        Some(self.closed.remove(i))
+    }
+
+    /// La más reciente primero, como en el menú
+    pub fn closed(&self) -> Vec<ClosedTab> {
+        self.closed.iter().rev().cloned().collect()
+    }
+
+    pub fn pending(&self) -> Option<&Session> {
+        self.pending.as_ref()
+    }
+
+    /// El usuario ha decidido (restaurar o descartar): la apartada sobra
+    pub fn take_pending(&mut self) -> Option<Session> {
+        let _ = fs::remove_file(self.dir.join(CRASHED_FILE));
+        self.pending.take()
+    }
+
+    /// Las fijadas de la sesión pendiente se restauran sin preguntar; en
+    /// la pendiente (y en el fichero apartado) queda el resto.
+    pub fn take_pending_pinned(&mut self) -> Option<Session> {
+        let (pinned, rest) = self.pending.take()?.split_pinned();
+        self.pending = Some(rest).filter(|s| !s.is_empty());
+        let crashed_file = self.dir.join(CRASHED_FILE);
+        match &self.pending {
+            Some(rest) => {
+                if let Ok(json) = serde_json::to_string_pretty(rest) {
+                    let _ = fs::write(&crashed_file, json);
+                }
+            }
+            None => {
+                let _ = fs::remove_file(&crashed_file);
+            }
+        }
+        Some(pinned).filter(|s| !s.is_empty())
+    }
+
+    /// Cierre limpio: escribe lo último y retira session.lock. Sin
+    /// `session` (solo quedaban ventanas privadas) vale la última guardada.
+    pub fn close(&mut self, session: Option<Session>) {
+        if let Some(session) = session.or_else(|| self.last.take()) {
+            let session = self.complete(session);
+            let (done, wait) = mpsc::channel();
+            if self.writer.send(Message::Flush(session, done)).is_ok() {
+                let _ = wait.recv_timeout(Duration::from_secs(2));
+            }
+        }
+        let _ = fs::remove_file(self.dir.join(LOCK_FILE));
+    }
+}
+
+fn write_loop(dir: &Path, messages: mpsc::Receiver<Message>) {
+    let mut pending: Option<(Session, Instant)> = None;
+    loop {
+        let message = match &pending {
+            Some((_, first)) => {
+                let wait = DEBOUNCE.min(MAX_DELAY.saturating_sub(first.elapsed()));
+                messages.recv_timeout(wait)
+            }
+            None => messages.recv().map_err(|_| RecvTimeoutError::Disconnected),
+        };
+        match message {
+            Ok(Message::Save(session)) => {
+                let first = pending.map_or_else(Instant::now, |(_, first)| first);
+                pending = Some((session, first));
+            }
+            Ok(Message::Flush(session, done)) => {
+                pending = None;
+                write_atomic(dir, &session);
+                let _ = done.send(());
+            }
+            Err(RecvTimeoutError::Timeout) => {
+                if let Some((session, _)) = pending.take() {
+                    write_atomic(dir, &session);
+                }
+            }
+            Err(RecvTimeoutError::Disconnected) => {
+                if let Some((session, _)) = pending.take() {
+                    write_atomic(dir, &session);
+                }
+                return;
+            }
+        }
+    }
+}
+
+/// Un corte a medio escribir deja el session.json anterior intacto.
+fn write_atomic(dir: &Path, session: &Session) {
+    let Ok(json) = serde_json::to_string_pretty(session) else {
+        return;
+    };
+    let tmp = dir.join(format!("{SESSION_FILE}.tmp"));
+    let written = fs::File::create(&tmp).and_then(|mut file| {
+        file.write_all(json.as_bytes())?;
+        file.sync_all()
+    });
+    if written.is_ok() {
+        let _ = fs::rename(&tmp, dir.join(SESSION_FILE));
+    }
+}
+
+pub type SessionState = Arc<Mutex<SessionStore>>;
@@ -0,0 +1,57 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+// ================================================================
+// AJUSTES — <perfil>/settings.json
+// ================================================================
+
+const SETTINGS_FILE: &str = "settings.json";
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// Restaurar todas las pestañas al arrancar. Las fijadas vuelven siempre.
+    pub restore_session: bool,
+    /// Navegar a otro origen desde una pestaña fijada abre una pestaña nueva
+    pub pinned_cross_origin_new_tab: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            restore_session: true,
+            pinned_cross_origin_new_tab: false,
+        }
+    }
+}
+
+pub struct SettingsStore {
+    path: PathBuf,
+    settings: Settings,
+}
+
+impl SettingsStore {
+    pub fn load(profile: PathBuf) -> Self {
+        let path = profile.join(SETTINGS_FILE);
+        let settings = fs::read_to_string(&path)
+            .ok()
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default();
+        Self { path, settings }
+    }
+
+    pub fn get(&self) -> &Settings {
+        &self.settings
+    }
+
+    pub fn set(&mut self, settings: Settings) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(&settings).map_err(|e| e.to_string())?;
+        fs::write(&self.path, json).map_err(|e| e.to_string())?;
+        self.settings = settings;
+        Ok(())
+    }
+}
+
+pub type SettingsState = Arc<Mutex<SettingsStore>>;
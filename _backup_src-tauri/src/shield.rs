@@ -0,0 +1,378 @@
+// ================================================================
+// REGISTRO DE MÓDULOS DEL SHIELD
+// ================================================================
+// Cada módulo declara en qué hosts se ejecuta, en qué momento, en qué
+// mundo y de qué otros módulos depende. La decisión de qué inyectar se
+// toma aquí, por navegación, en lugar de que cada IIFE compruebe el
+// host en JS y se salga.
+//
+// Los scripts de inicio de documento llegan también a los iframes, que
+// pueden ser de otro host: script_for envuelve cada módulo en una
+// comprobación de location.hostname generada a partir de este registro.
+// Solo van los módulos que aplican al documento principal; un iframe de
+// otro host no recibe los que serían solo suyos.
+//
+// En release el código de cada módulo es la copia embebida (comprobada
+// contra scripts/manifest.txt en build.rs). En debug se lee de un
+// directorio y se recarga en caliente: ver dev_dir().
+// ================================================================
+
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum RunAt {
+    /// Antes de cualquier script de la página
+    DocumentStart,
+    /// Tras la carga (on_page_load Finished)
+    DocumentIdle,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum World {
+    /// Mundo de la página: necesario para mocks y traps sobre `window`
+    Page,
+    /// Solo toca el DOM. En WebKitGTK va a un mundo aislado propio;
+    /// WebView2 no tiene mundos aislados, así que ahí se ejecuta en la
+    /// página igualmente.
+    Isolated,
+}
+
+/// Por qué camino llegó un módulo al documento
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Phase {
+    /// Inyección temprana (AddScriptToExecuteOnDocumentCreated / UserScript)
+    Early,
+    /// inject_shield_fallback desde on_page_load
+    Fallback,
+}
+
+pub struct ShieldModule {
+    pub id: &'static str,
+    pub source: &'static str,
+    /// Hosts donde aplica ("*" = todos). Vacío: solo como dependencia.
+    pub matches: &'static [&'static str],
+    pub excludes: &'static [&'static str],
+    pub run_at: RunAt,
+    pub world: World,
+    pub requires: &'static [&'static str],
+}
+
+const ALL_HOSTS: &[&str] = &["*"];
+const YOUTUBE: &[&str] = &["youtube.com"];
+const CORE: &[&str] = &["m0_core"];
+
+// Orden de registro = orden de inyección. Las dependencias van antes.
+pub const MODULES: &[ShieldModule] = &[
+    ShieldModule {
+        id: "m0_core",
+        source: include_str!("../scripts/m0_core.js"),
+        matches: &[],
+        excludes: &[],
+        run_at: RunAt::DocumentStart,
+        world: World::Page,
+        requires: &[],
+    },
+    ShieldModule {
+        id: "m1_youtube",
+        source: include_str!("../scripts/m1_youtube.js"),
+        matches: YOUTUBE,
+        excludes: &[],
+        run_at: RunAt::DocumentStart,
+        world: World::Page,
+        requires: CORE,
+    },
+    ShieldModule {
+        id: "m2_youtube_cosmetics",
+        source: include_str!("../scripts/m2_youtube_cosmetics.js"),
+        matches: YOUTUBE,
+        excludes: &[],
+        run_at: RunAt::DocumentStart,
+        world: World::Page,
+        requires: CORE,
+    },
+    ShieldModule {
+        id: "m3_gpt_mock",
+        source: include_str!("../scripts/m3_gpt_mock.js"),
+        matches: ALL_HOSTS,
+        excludes: YOUTUBE,
+        run_at: RunAt::DocumentStart,
+        world: World::Page,
+        requires: CORE,
+    },
+    ShieldModule {
+        id: "m4_ad_mocks",
+        source: include_str!("../scripts/m4_ad_mocks.js"),
+        matches: ALL_HOSTS,
+        excludes: YOUTUBE,
+        run_at: RunAt::DocumentStart,
+        world: World::Page,
+        requires: CORE,
+    },
+    ShieldModule {
+        id: "m5_anti_detection",
+        source: include_str!("../scripts/m5_anti_detection.js"),
+        matches: ALL_HOSTS,
+        excludes: YOUTUBE,
+        run_at: RunAt::DocumentStart,
+        world: World::Page,
+        requires: CORE,
+    },
+    ShieldModule {
+        id: "m6_network_intercept",
+        source: include_str!("../scripts/m6_network_intercept.js"),
+        matches: ALL_HOSTS,
+        excludes: YOUTUBE,
+        run_at: RunAt::DocumentStart,
+        world: World::Page,
+        requires: CORE,
+    },
+    ShieldModule {
+        id: "m7_cosmetics",
+        source: include_str!("../scripts/m7_cosmetics.js"),
+        matches: ALL_HOSTS,
+        excludes: YOUTUBE,
+        run_at: RunAt::DocumentStart,
+        world: World::Isolated,
+        requires: CORE,
+    },
+    ShieldModule {
+        id: "m9_youtube_accelerate",
+        source: include_str!("../scripts/m9_youtube_accelerate.js"),
+        matches: YOUTUBE,
+        excludes: &[],
+        run_at: RunAt::DocumentIdle,
+        world: World::Page,
+        requires: CORE,
+    },
+];
+
+/// "youtube.com" cubre el dominio y todos sus subdominios.
+pub fn host_matches(pattern: &str, host: &str) -> bool {
+    pattern == "*"
+        || host == pattern
+        || host
+            .strip_suffix(pattern)
+            .is_some_and(|rest| rest.ends_with('.'))
+}
+
+impl ShieldModule {
+    /// Código vigente del módulo: el del directorio de desarrollo si se
+    /// pudo leer, si no la copia embebida.
+    #[cfg(debug_assertions)]
+    fn current_source(&self) -> Cow<'static, str> {
+        dev_sources()
+            .read()
+            .ok()
+            .and_then(|sources| sources.get(self.id).cloned())
+            .map(Cow::Owned)
+            .unwrap_or(Cow::Borrowed(self.source))
+    }
+
+    #[cfg(not(debug_assertions))]
+    fn current_source(&self) -> Cow<'static, str> {
+        Cow::Borrowed(self.source)
+    }
+
+    fn applies_to(&self, host: &str) -> bool {
+        self.matches.iter().any(|p| host_matches(p, host))
+            && !self.excludes.iter().any(|p| host_matches(p, host))
+    }
+}
+
+fn find(id: &str) -> Option<&'static ShieldModule> {
+    MODULES.iter().find(|m| m.id == id)
+}
+
+/// Módulos que aplican a `url`, con sus dependencias, en orden de registro.
+pub fn modules_for(url: &str) -> Vec<&'static ShieldModule> {
+    let host = match tauri::Url::parse(url) {
+        Ok(u) if matches!(u.scheme(), "http" | "https") => {
+            u.host_str().unwrap_or_default().to_lowercase()
+        }
+        _ => return Vec::new(),
+    };
+
+    let mut selected: Vec<&str> = Vec::new();
+    let mut pending: Vec<&str> = MODULES
+        .iter()
+        .filter(|m| m.applies_to(&host))
+        .map(|m| m.id)
+        .collect();
+
+    while let Some(id) = pending.pop() {
+        if selected.contains(&id) {
+            continue;
+        }
+        selected.push(id);
+        if let Some(module) = find(id) {
+            pending.extend(module.requires.iter().copied());
+        }
+    }
+
+    MODULES
+        .iter()
+        .filter(|m| selected.contains(&m.id))
+        .collect()
+}
+
+/// Condición JS equivalente a `applies_to` para el frame en curso. Un
+/// módulo que solo es dependencia se ejecuta si aplica alguno de los
+/// módulos de `modules` que lo requieren.
+fn host_guard(module: &ShieldModule, modules: &[&ShieldModule]) -> String {
+    if !module.matches.is_empty() {
+        let list = |hosts: &[&str]| serde_json::to_string(hosts).unwrap_or_default();
+        return format!(
+            "applies({}, {})",
+            list(module.matches),
+            list(module.excludes)
+        );
+    }
+    let mut dependents: Vec<String> = Vec::new();
+    for dependent in modules.iter().filter(|m| m.requires.contains(&module.id)) {
+        let guard = host_guard(dependent, modules);
+        if !dependents.contains(&guard) {
+            dependents.push(guard);
+        }
+    }
+    if dependents.is_empty() {
+        "false".to_string()
+    } else {
+        dependents.join(" || ")
+    }
+}
+
+// Mismo criterio que host_matches, sobre el host del frame
+const APPLIES: &str = r#"function applies(matches, excludes) {
+    if (!/^https?:$/.test(location.protocol)) return false;
+    var host = (location.hostname || '').toLowerCase();
+    function hit(p) {
+        return p === '*' || host === p ||
+            (host.length > p.length && host.slice(-p.length - 1) === '.' + p);
+    }
+    return matches.some(hit) && !excludes.some(hit);
+}
+"#;
+
+/// `webview.eval` y WebView2 no distinguen mundos: todo acaba en la página.
+pub const ALL_WORLDS: &[World] = &[World::Page, World::Isolated];
+
+// Cada módulo va en su propio try/catch y avisa a Rust al terminar
+// (comando shield_report, ver diagnostics.rs). Solo hay IPC de Tauri en
+// el mundo de la página: desde un mundo aislado o un frame sin IPC el
+// aviso se reenvía por postMessage al documento principal.
+const REPORTER: &str = r#"(function (phase) {
+    var ipc = window.__TAURI_INTERNALS__;
+    var top = window.top === window;
+    function send(report) {
+        ipc.invoke('shield_report', { report: report }).catch(function () { });
+    }
+    if (ipc && top && !window.__atomShieldRelay) {
+        window.__atomShieldRelay = true;
+        window.addEventListener('message', function (e) {
+            var report = e.data && e.data.__atomShieldReport;
+            if (report && typeof report.module === 'string') send(report);
+        });
+    }
+    return function (module, error) {
+        if (!/^https?:$/.test(location.protocol)) return;
+        var report = {
+            module: module,
+            frame_url: location.href,
+            top: top,
+            phase: phase,
+            error: error ? String((error && error.stack) || error) : null
+        };
+        if (ipc && ipc.invoke) send(report);
+        else try { window.top.postMessage({ __atomShieldReport: report }, '*'); } catch (_) { }
+    };
+})"#;
+
+/// Concatena en un único script los módulos de `run_at` destinados a `worlds`.
+/// Un mundo aislado no ve el `window.__atomShield` de la página, así que las
+/// dependencias de otro mundo se repiten aquí. Cada módulo se salta en los
+/// frames cuyo host no le corresponde.
+pub fn script_for(
+    modules: &[&ShieldModule],
+    run_at: RunAt,
+    worlds: &[World],
+    phase: Phase,
+) -> String {
+    let mut needed: Vec<&str> = modules
+        .iter()
+        .filter(|m| m.run_at == run_at && worlds.contains(&m.world))
+        .map(|m| m.id)
+        .collect();
+
+    let mut i = 0;
+    while i < needed.len() {
+        if let Some(module) = find(needed[i]) {
+            for dep in module.requires.iter().filter_map(|&id| find(id)) {
+                if !worlds.contains(&dep.world) && !needed.contains(&dep.id) {
+                    needed.push(dep.id);
+                }
+            }
+        }
+        i += 1;
+    }
+    if needed.is_empty() {
+        return String::new();
+    }
+
+    let phase = match phase {
+        Phase::Early => "early",
+        Phase::Fallback => "fallback",
+    };
+    let mut combined = String::from("(function (report) {\n");
+    combined.push_str(APPLIES);
+    for module in modules.iter().filter(|m| needed.contains(&m.id)) {
+        let id = module.id;
+        combined.push_str(&format!("if ({}) try {{\n", host_guard(module, modules)));
+        combined.push_str(&module.current_source());
+        combined.push_str(&format!(
+            "\nreport('{id}', null);\n}} catch (e) {{ report('{id}', e); }}\n"
+        ));
+    }
+    combined.push_str(&format!("}})({REPORTER}('{phase}'));\n"));
+    combined
+}
+
+// ================================================================
+// MÓDULOS EN DESARROLLO — recarga en caliente (solo debug)
+// ================================================================
+
+/// Código leído del directorio de desarrollo, por id de módulo.
+#[cfg(debug_assertions)]
+type DevSources = std::sync::RwLock<std::collections::HashMap<&'static str, String>>;
+
+#[cfg(debug_assertions)]
+fn dev_sources() -> &'static DevSources {
+    static SOURCES: std::sync::OnceLock<DevSources> = std::sync::OnceLock::new();
+    SOURCES.get_or_init(Default::default)
+}
+
+/// Directorio de los módulos en desarrollo: `ATOM_SHIELD_DIR` o, por
+/// defecto, el scripts/ del propio crate.
+#[cfg(debug_assertions)]
+pub fn dev_dir() -> std::path::PathBuf {
+    std::env::var_os("ATOM_SHIELD_DIR")
+        .map(std::path::PathBuf::from)
+        .unwrap_or_else(|| concat!(env!("CARGO_MANIFEST_DIR"), "/scripts").into())
+}
+
+/// Lee `<id>.js` de `dir` para cada módulo registrado. Los que falten o
+/// no se puedan leer vuelven a la copia embebida.
+#[cfg(debug_assertions)]
+pub fn load_dev_sources(dir: &std::path::Path) {
+    let sources = MODULES
+        .iter()
+        .filter_map(|m| {
+            let source = std::fs::read_to_string(dir.join(format!("{}.js", m.id))).ok()?;
+            Some((m.id, source))
+        })
+        .collect();
+    if let Ok(mut current) = dev_sources().write() {
+        *current = sources;
+    }
+}
@@ -0,0 +1,220 @@
+use serde::Serialize;
+use std::collections::HashSet;
+
+// ================================================================
+// BUSCADOR RÁPIDO — pestañas, cerradas, marcadores e historial
+// ================================================================
+// Se llama en cada tecla, así que no hay índice: una pasada por todo
+// comparando en minúsculas. Cada palabra de la búsqueda tiene que
+// aparecer en el título o en la URL, seguida o como subsecuencia
+// ("gthb" → "github"). Manda la calidad del encaje; a igualdad, lo
+// reciente, lo visitado a menudo y lo que ya está abierto.
+// ================================================================
+
+pub const MAX_RESULTS: usize = 20;
+
+/// Puntos extra por tipo, sobre 100 de un encaje perfecto
+const TAB_BONUS: f64 = 15.0;
+const BOOKMARK_BONUS: f64 = 10.0;
+const CLOSED_BONUS: f64 = 5.0;
+/// Como mucho, por uso reciente y por número de visitas
+const RECENCY_POINTS: f64 = 20.0;
+const FREQUENCY_POINTS: f64 = 20.0;
+/// Lo visitado hace un día vale la mitad que lo de ahora mismo
+const RECENCY_HALF_LIFE_MS: f64 = 24.0 * 3600.0 * 1000.0;
+
+#[derive(Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SwitchAction {
+    SwitchTab {
+        tab_id: String,
+        window: String,
+    },
+    /// `index` como en list_recently_closed: 0 es la más reciente
+    Reopen {
+        index: usize,
+    },
+    Navigate {
+        url: String,
+    },
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SwitchKind {
+    Tab,
+    Closed,
+    Bookmark,
+    History,
+}
+
+#[derive(Serialize)]
+pub struct SwitchResult {
+    pub kind: SwitchKind,
+    pub title: String,
+    pub url: String,
+    pub action: SwitchAction,
+    pub score: f64,
+}
+
+pub enum Target<'a> {
+    Tab { id: &'a str, window: &'a str },
+    Closed { index: usize },
+    Bookmark,
+    History,
+}
+
+/// Lo que se compara; prestado para no copiar miles de entradas por tecla
+pub struct Candidate<'a> {
+    pub title: &'a str,
+    pub url: &'a str,
+    pub target: Target<'a>,
+    /// Milisegundos desde 1970; 0 = no se sabe
+    pub last_used: u64,
+    pub visits: u32,
+}
+
+impl Candidate<'_> {
+    fn kind(&self) -> SwitchKind {
+        match self.target {
+            Target::Tab { .. } => SwitchKind::Tab,
+            Target::Closed { .. } => SwitchKind::Closed,
+            Target::Bookmark => SwitchKind::Bookmark,
+            Target::History => SwitchKind::History,
+        }
+    }
+
+    fn action(&self) -> SwitchAction {
+        match self.target {
+            Target::Tab { id, window } => SwitchAction::SwitchTab {
+                tab_id: id.to_string(),
+                window: window.to_string(),
+            },
+            Target::Closed { index } => SwitchAction::Reopen { index },
+            Target::Bookmark | Target::History => SwitchAction::Navigate {
+                url: self.url.to_string(),
+            },
+        }
+    }
+}
+
+/// Los mejores MAX_RESULTS para `query`. Una URL abierta en una pestaña
+/// sale solo como esa pestaña; repetida en otras fuentes, solo la mejor.
+pub fn search<'a>(
+    query: &str,
+    candidates: impl IntoIterator<Item = Candidate<'a>>,
+    now: u64,
+) -> Vec<SwitchResult> {
+    let query = query.to_lowercase();
+    let terms: Vec<&str> = query.split_whitespace().collect();
+    let candidates: Vec<Candidate> = candidates.into_iter().collect();
+    let open: HashSet<&str> = candidates
+        .iter()
+        .filter(|c| matches!(c.target, Target::Tab { .. }))
+        .map(|c| c.url)
+        .collect();
+
+    let mut scored: Vec<(f64, &Candidate)> = candidates
+        .iter()
+        .filter(|c| matches!(c.target, Target::Tab { .. }) || !open.contains(c.url))
+        .filter_map(|c| Some((score(&terms, c, now)?, c)))
+        .collect();
+    scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+    let mut seen = HashSet::new();
+    scored
+        .into_iter()
+        .filter(|(_, c)| matches!(c.target, Target::Tab { .. }) || seen.insert(c.url))
+        .take(MAX_RESULTS)
+        .map(|(score, c)| SwitchResult {
+            kind: c.kind(),
+            title: c.title.to_string(),
+            url: c.url.to_string(),
+            action: c.action(),
+            score,
+        })
+        .collect()
+}
+
+fn score(terms: &[&str], candidate: &Candidate, now: u64) -> Option<f64> {
+    let quality = if terms.is_empty() {
+        1.0
+    } else {
+        let title = candidate.title.to_lowercase();
+        let url = display_url(candidate.url).to_lowercase();
+        let mut total = 0.0;
+        for term in terms {
+            let in_title = match_quality(term, &title);
+            // La URL cuenta algo menos: casi todo encaja en algún sitio de ella
+            let in_url = match_quality(term, &url).map(|q| q * 0.9);
+            total += in_title.into_iter().chain(in_url).reduce(f64::max)?;
+        }
+        total / terms.len() as f64
+    };
+
+    let bonus = match candidate.target {
+        Target::Tab { .. } => TAB_BONUS,
+        Target::Bookmark => BOOKMARK_BONUS,
+        Target::Closed { .. } => CLOSED_BONUS,
+        Target::History => 0.0,
+    };
+    let recency = if candidate.last_used == 0 {
+        0.0
+    } else {
+        let age = now.saturating_sub(candidate.last_used) as f64;
+        RECENCY_POINTS * 0.5f64.powf(age / RECENCY_HALF_LIFE_MS)
+    };
+    let frequency =
+        (FREQUENCY_POINTS * (candidate.visits as f64).ln_1p() / 5.0).min(FREQUENCY_POINTS);
+    Some(quality * 100.0 + bonus + recency + frequency)
+}
+
+/// Sin "https://" ni "www.": que "h" o "w" no encajen en todas
+fn display_url(url: &str) -> &str {
+    let url = url.split_once("://").map(|(_, rest)| rest).unwrap_or(url);
+    url.strip_prefix("www.").unwrap_or(url)
+}
+
+/// Entre 0 y 1; None si `term` no aparece ni como subsecuencia. Seguido
+/// vale más que salteado, y al principio de una palabra más que en medio.
+fn match_quality(term: &str, text: &str) -> Option<f64> {
+    if let Some(pos) = text.find(term) {
+        return Some(if pos == 0 {
+            1.0
+        } else if text[..pos].ends_with(is_separator) {
+            0.9
+        } else {
+            0.7
+        });
+    }
+
+    // Subsecuencia: cada salto en medio de una palabra penaliza
+    let mut wanted = term.chars().peekable();
+    let mut breaks = 0;
+    let mut previous: Option<char> = None;
+    let mut previous_matched = false;
+    for c in text.chars() {
+        let Some(&w) = wanted.peek() else {
+            break;
+        };
+        if c == w {
+            let at_start = previous.map(is_separator).unwrap_or(true);
+            if !previous_matched && !at_start {
+                breaks += 1;
+            }
+            wanted.next();
+            previous_matched = true;
+        } else {
+            previous_matched = false;
+        }
+        previous = Some(c);
+    }
+    if wanted.peek().is_some() {
+        return None;
+    }
+    Some(0.2 + 0.4 / (1.0 + breaks as f64))
+}
+
+fn is_separator(c: char) -> bool {
+    !c.is_alphanumeric()
+}
@@ -0,0 +1,570 @@
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use crate::containers::Container;
+use crate::layout::Sidebars;
+
+/// Entradas de historial que se conservan por pestaña
+const MAX_HISTORY: usize = 50;
+
+// ================================================================
+// MODELO DE PESTAÑAS — ventanas, orden, pestaña activa y opener
+// ================================================================
+// Cada ventana lleva sus pestañas en el orden de su barra: la UI de
+// cada una lo pide con list_tabs en lugar de llevar su propia copia.
+// Cada cambio de estado de una pestaña se emite entero en "tab-updated"
+// a su ventana.
+// ================================================================
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Security {
+    /// https
+    Secure,
+    /// http
+    Insecure,
+    /// Páginas propias y esquemas sin red (atom, file, about, data…)
+    Local,
+}
+
+impl Security {
+    fn of(url: &str) -> Self {
+        match tauri::Url::parse(url).as_ref().map(tauri::Url::scheme) {
+            Ok("https") => Security::Secure,
+            Ok("http") => Security::Insecure,
+            _ => Security::Local,
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Serialize)]
+pub struct Tab {
+    pub id: String,
+    pub url: String,
+    /// Pestaña desde la que se abrió (enlace, window.open, popup permitido)
+    pub opener: Option<String>,
+    pub title: String,
+    pub favicon: Option<String>,
+    pub loading: bool,
+    pub can_go_back: bool,
+    pub can_go_forward: bool,
+    pub security: Security,
+    /// Reproduciendo sonido ahora mismo
+    pub audible: bool,
+    pub muted: bool,
+    pub pinned: bool,
+    /// Contenedor con sus propias cookies; None = el perfil normal
+    pub container: Option<Container>,
+    /// Atrás/adelante tal como se ve desde aquí, para la sesión: WebView2
+    /// no deja leer su lista ni ningún motor reconstruirla.
+    pub history: Vec<String>,
+    pub history_index: usize,
+    /// Sin webview: descartada o restaurada sin cargar. Se carga al activarla.
+    pub discarded: bool,
+    /// Texto escrito en un formulario sin enviar
+    pub form_dirty: bool,
+    /// En su ventana flotante (picture-in-picture): sigue en la barra de
+    /// su ventana, pero su webview no está en ella
+    pub pip: bool,
+    /// Última vez que fue la pestaña activa
+    #[serde(skip)]
+    pub last_active: Instant,
+}
+
+impl Tab {
+    pub fn new(id: String, url: String, opener: Option<String>) -> Self {
+        Self {
+            security: Security::of(&url),
+            history: vec![url.clone()],
+            history_index: 0,
+            id,
+            url,
+            opener,
+            title: String::new(),
+            favicon: None,
+            loading: true,
+            can_go_back: false,
+            can_go_forward: false,
+            audible: false,
+            muted: false,
+            pinned: false,
+            container: None,
+            discarded: false,
+            form_dirty: false,
+            pip: false,
+            last_active: Instant::now(),
+        }
+    }
+
+    /// Copia sin webview para duplicarla: misma URL, historial y contenedor
+    pub fn duplicate(&self, id: String) -> Self {
+        let mut tab = Tab::new(id, self.url.clone(), None);
+        tab.title = self.title.clone();
+        tab.favicon = self.favicon.clone();
+        tab.pinned = self.pinned;
+        tab.container = self.container.clone();
+        tab.history = self.history.clone();
+        tab.history_index = self.history_index;
+        tab
+    }
+
+    /// Cambia la URL y con ella el estado de seguridad. El favicon solo
+    /// vale para el mismo origen.
+    pub fn set_url(&mut self, url: &str) {
+        if origin_of(&self.url) != origin_of(url) {
+            self.favicon = None;
+        }
+        self.url = url.to_string();
+        self.security = Security::of(url);
+        self.record_history(url);
+    }
+
+    /// Volver a la entrada anterior o siguiente se reconoce por la URL;
+    /// cualquier otra es una navegación nueva que corta el "adelante".
+    fn record_history(&mut self, url: &str) {
+        let at = |i: usize| self.history.get(i).map(String::as_str);
+        if at(self.history_index) == Some(url) {
+            return;
+        }
+        if self.history_index > 0 && at(self.history_index - 1) == Some(url) {
+            self.history_index -= 1;
+        } else if at(self.history_index + 1) == Some(url) {
+            self.history_index += 1;
+        } else {
+            self.history.truncate(self.history_index + 1);
+            self.history.push(url.to_string());
+            if self.history.len() > MAX_HISTORY {
+                self.history.remove(0);
+            }
+            self.history_index = self.history.len() - 1;
+        }
+    }
+
+    /// `/favicon.ico` del origen si la página no ha declarado otro
+    pub fn default_favicon(&mut self) {
+        if self.favicon.is_none() && self.security != Security::Local {
+            self.favicon = origin_of(&self.url).map(|origin| format!("{origin}/favicon.ico"));
+        }
+    }
+}
+
+fn origin_of(url: &str) -> Option<String> {
+    let url = tauri::Url::parse(url).ok()?;
+    Some(url.origin().ascii_serialization()).filter(|o| o != "null")
+}
+
+/// Ventana creada desde tauri.conf.json
+pub const MAIN_WINDOW: &str = "main";
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SplitOrientation {
+    /// Una al lado de la otra
+    Horizontal,
+    /// Una encima de la otra
+    Vertical,
+}
+
+/// Vista dividida: dos pestañas a la vez bajo la barra. La activa es
+/// una de las dos; activar cualquier otra la termina.
+#[derive(Clone, Serialize)]
+pub struct Split {
+    /// Izquierda/arriba y derecha/abajo
+    pub panes: [String; 2],
+    pub orientation: SplitOrientation,
+    /// Parte del espacio que ocupa la primera
+    pub ratio: f64,
+}
+
+impl Split {
+    pub fn contains(&self, id: &str) -> bool {
+        self.panes.iter().any(|p| p == id)
+    }
+
+    fn partner(&self, id: &str) -> Option<&str> {
+        match &self.panes {
+            [a, b] if a == id => Some(b),
+            [a, b] if b == id => Some(a),
+            _ => None,
+        }
+    }
+}
+
+const MIN_SPLIT_RATIO: f64 = 0.1;
+
+/// Una ventana del navegador con sus pestañas en el orden de su barra
+#[derive(Clone, Serialize)]
+pub struct BrowserWindow {
+    pub label: String,
+    pub tabs: Vec<Tab>,
+    pub active_tab: Option<String>,
+    /// Pantalla completa pedida por el usuario (F11)
+    pub is_fullscreen: bool,
+    /// Pestaña cuya página está en pantalla completa (un vídeo): solo ella
+    /// ocupa la ventana, sin barra
+    pub page_fullscreen: Option<String>,
+    /// Navegación privada: datos aparte que se borran al cerrarla
+    pub private: bool,
+    pub split: Option<Split>,
+    pub sidebars: Sidebars,
+    #[serde(skip)]
+    pub pre_fs_pos: Option<(i32, i32)>,
+    #[serde(skip)]
+    pub pre_fs_size: Option<(u32, u32)>,
+}
+
+impl BrowserWindow {
+    pub fn new(label: &str, private: bool) -> Self {
+        Self {
+            label: label.to_string(),
+            tabs: Vec::new(),
+            active_tab: None,
+            is_fullscreen: false,
+            page_fullscreen: None,
+            private,
+            split: None,
+            sidebars: Sidebars::default(),
+            pre_fs_pos: None,
+            pre_fs_size: None,
+        }
+    }
+
+    pub fn index_of(&self, id: &str) -> Option<usize> {
+        self.tabs.iter().position(|t| t.id == id)
+    }
+
+    fn pinned_count(&self) -> usize {
+        self.tabs.iter().filter(|t| t.pinned).count()
+    }
+
+    /// Las fijadas van siempre delante: cada pestaña se queda en su grupo.
+    fn clamp_to_group(&self, pinned: bool, index: usize) -> usize {
+        let first_unpinned = self.pinned_count();
+        if pinned {
+            index.min(first_unpinned)
+        } else {
+            index.clamp(first_unpinned, self.tabs.len())
+        }
+    }
+
+    /// Cambia la activa y anota cuándo dejó de serlo la anterior.
+    pub fn set_active(&mut self, id: &str) {
+        let now = Instant::now();
+        for tab in self.tabs.iter_mut() {
+            if tab.id == id || self.active_tab.as_deref() == Some(tab.id.as_str()) {
+                tab.last_active = now;
+            }
+        }
+        self.active_tab = Some(id.to_string());
+    }
+
+    /// Pestañas a la vista: las dos de la vista dividida o la activa
+    pub fn visible(&self) -> Vec<String> {
+        match &self.split {
+            Some(split) => split.panes.to_vec(),
+            None => self.active_tab.iter().cloned().collect(),
+        }
+    }
+
+    /// Activa la pestaña, terminando la vista dividida si no es de ella y
+    /// la pantalla completa de otra página. Devuelve las que dejan de verse.
+    /// Como en Chrome, la que deja de ser activa olvida a su opener: al
+    /// cerrarla más tarde ya no se vuelve a él.
+    pub fn activate(&mut self, id: &str) -> Vec<String> {
+        if let Some(previous) = self.active_tab.clone().filter(|p| p != id) {
+            if let Some(tab) = self.tabs.iter_mut().find(|t| t.id == previous) {
+                tab.opener = None;
+            }
+        }
+        let before = self.visible();
+        if !self.split.as_ref().is_some_and(|s| s.contains(id)) {
+            self.split = None;
+        }
+        if self.page_fullscreen.as_deref() != Some(id) {
+            self.page_fullscreen = None;
+        }
+        self.set_active(id);
+        let after = self.visible();
+        before.into_iter().filter(|t| !after.contains(t)).collect()
+    }
+
+    /// La activa a la izquierda (o arriba) y `other` al lado. Devuelve
+    /// las que dejan de verse (de otra vista dividida anterior).
+    pub fn enter_split(
+        &mut self,
+        other: &str,
+        orientation: SplitOrientation,
+    ) -> Result<Vec<String>, String> {
+        let active = self.active_tab.clone().ok_or("No active tab")?;
+        if self.index_of(other).is_none() {
+            return Err("Tab not found".to_string());
+        }
+        if active == other {
+            return Err("Cannot split a tab with itself".to_string());
+        }
+        let before = self.visible();
+        self.split = Some(Split {
+            panes: [active, other.to_string()],
+            orientation,
+            ratio: 0.5,
+        });
+        let after = self.visible();
+        Ok(before.into_iter().filter(|t| !after.contains(t)).collect())
+    }
+
+    /// Se queda solo la activa. Devuelve la otra mitad.
+    pub fn exit_split(&mut self) -> Result<Vec<String>, String> {
+        let split = self.split.take().ok_or("Not in split view")?;
+        let active = self.active_tab.as_deref();
+        Ok(split
+            .panes
+            .into_iter()
+            .filter(|p| Some(p.as_str()) != active)
+            .collect())
+    }
+
+    pub fn swap_split(&mut self) -> Result<(), String> {
+        let split = self.split.as_mut().ok_or("Not in split view")?;
+        split.panes.swap(0, 1);
+        Ok(())
+    }
+
+    pub fn set_split_ratio(&mut self, ratio: f64) -> Result<(), String> {
+        let split = self.split.as_mut().ok_or("Not in split view")?;
+        if !ratio.is_finite() {
+            return Err("Invalid ratio".to_string());
+        }
+        split.ratio = ratio.clamp(MIN_SPLIT_RATIO, 1.0 - MIN_SPLIT_RATIO);
+        Ok(())
+    }
+
+    /// Sin opener va al final. Abierta desde otra pestaña, justo después
+    /// de ella y de las que esa ya abrió seguidas, como en Chrome o Firefox.
+    pub fn insert(&mut self, tab: Tab) {
+        let position = tab
+            .opener
+            .as_deref()
+            .and_then(|opener| {
+                let mut i = self.index_of(opener)? + 1;
+                while self
+                    .tabs
+                    .get(i)
+                    .is_some_and(|t| t.opener.as_deref() == Some(opener))
+                {
+                    i += 1;
+                }
+                Some(i)
+            })
+            .unwrap_or(self.tabs.len());
+        let position = self.clamp_to_group(tab.pinned, position);
+        self.tabs.insert(position, tab);
+    }
+
+    /// En una posición concreta (pestaña reabierta); fuera de rango = al final.
+    pub fn insert_at(&mut self, tab: Tab, index: usize) {
+        let index = self.clamp_to_group(tab.pinned, index);
+        self.tabs.insert(index, tab);
+    }
+
+    /// `index` fuera de rango = al final de su grupo.
+    pub fn move_tab(&mut self, id: &str, index: usize) -> Result<(), String> {
+        let from = self.index_of(id).ok_or("Tab not found")?;
+        let tab = self.tabs.remove(from);
+        let index = self.clamp_to_group(tab.pinned, index);
+        self.tabs.insert(index, tab);
+        Ok(())
+    }
+
+    /// Fija o suelta la pestaña y la deja en el borde entre los dos
+    /// grupos. Devuelve si cambió algo.
+    pub fn set_pinned(&mut self, id: &str, pinned: bool) -> Result<bool, String> {
+        let from = self.index_of(id).ok_or("Tab not found")?;
+        if self.tabs[from].pinned == pinned {
+            return Ok(false);
+        }
+        let mut tab = self.tabs.remove(from);
+        tab.pinned = pinned;
+        let index = self.pinned_count();
+        self.tabs.insert(index, tab);
+        Ok(true)
+    }
+
+    /// Saca la pestaña a su ventana flotante. Sigue en la barra, pero deja
+    /// la vista dividida y de ser la activa: pasa a serlo la otra mitad o
+    /// la vecina. Devuelve la nueva activa, si cambia.
+    pub fn pop_out(&mut self, id: &str) -> Result<Option<String>, String> {
+        let index = self.index_of(id).ok_or("Tab not found")?;
+        if self.tabs[index].pip {
+            return Err("Tab is already in picture-in-picture".to_string());
+        }
+        self.tabs[index].pip = true;
+
+        if self.page_fullscreen.as_deref() == Some(id) {
+            self.page_fullscreen = None;
+        }
+        let partner = self
+            .split
+            .take_if(|s| s.contains(id))
+            .and_then(|s| s.partner(id).map(str::to_string));
+        if self.active_tab.as_deref() != Some(id) {
+            return Ok(None);
+        }
+        let next = partner.or_else(|| {
+            let docked = |t: &&Tab| !t.pip;
+            self.tabs[index..]
+                .iter()
+                .find(docked)
+                .or_else(|| self.tabs[..index].iter().rev().find(docked))
+                .map(|t| t.id.clone())
+        });
+        match &next {
+            Some(next) => self.set_active(next),
+            None => self.active_tab = None,
+        }
+        Ok(next)
+    }
+
+    /// La vuelve a su sitio en la barra; activarla es cosa de quien llama.
+    pub fn dock(&mut self, id: &str) -> Result<(), String> {
+        let index = self.index_of(id).ok_or("Tab not found")?;
+        if !self.tabs[index].pip {
+            return Err("Tab is not in picture-in-picture".to_string());
+        }
+        self.tabs[index].pip = false;
+        Ok(())
+    }
+
+    /// Quita la pestaña. Si era la activa, pasa a serlo su opener si sigue
+    /// abierto; si no, la vecina de la derecha (o la de la izquierda si
+    /// era la última).
+    /// Si estaba en la vista dividida, esta termina y la otra mitad se
+    /// queda como activa.
+    pub fn remove(&mut self, id: &str) -> Option<Tab> {
+        let index = self.index_of(id)?;
+        let tab = self.tabs.remove(index);
+
+        if self.page_fullscreen.as_deref() == Some(id) {
+            self.page_fullscreen = None;
+        }
+        let partner = self
+            .split
+            .take_if(|s| s.contains(id))
+            .and_then(|s| s.partner(id).map(str::to_string));
+        if self.active_tab.as_deref() == Some(id) {
+            let opener = tab.opener.as_deref().filter(|o| self.index_of(o).is_some());
+            self.active_tab = match (partner, opener) {
+                (Some(partner), _) => Some(partner),
+                (None, Some(opener)) => Some(opener.to_string()),
+                (None, None) => self
+                    .tabs
+                    .get(index)
+                    .or_else(|| self.tabs.last())
+                    .map(|t| t.id.clone()),
+            };
+        }
+        Some(tab)
+    }
+}
+
+/// Registro de ventanas. Los ids de pestaña son únicos entre todas: son
+/// también las etiquetas de sus webviews.
+pub struct TabManager {
+    pub windows: Vec<BrowserWindow>,
+    counter: u32,
+    window_counter: u32,
+}
+
+impl TabManager {
+    pub fn new() -> Self {
+        Self {
+            windows: vec![BrowserWindow::new(MAIN_WINDOW, false)],
+            counter: 0,
+            window_counter: 0,
+        }
+    }
+
+    pub fn new_id(&mut self) -> String {
+        self.counter += 1;
+        format!("tab-{}", self.counter)
+    }
+
+    /// Registra una ventana nueva y devuelve su etiqueta
+    pub fn add_window(&mut self, private: bool) -> String {
+        self.window_counter += 1;
+        let label = format!("window-{}", self.window_counter);
+        self.windows.push(BrowserWindow::new(&label, private));
+        label
+    }
+
+    pub fn remove_window(&mut self, label: &str) -> Option<BrowserWindow> {
+        let index = self.windows.iter().position(|w| w.label == label)?;
+        Some(self.windows.remove(index))
+    }
+
+    pub fn window(&self, label: &str) -> Option<&BrowserWindow> {
+        self.windows.iter().find(|w| w.label == label)
+    }
+
+    pub fn window_mut(&mut self, label: &str) -> Option<&mut BrowserWindow> {
+        self.windows.iter_mut().find(|w| w.label == label)
+    }
+
+    /// Ventana que contiene la pestaña
+    pub fn window_of(&self, id: &str) -> Option<&BrowserWindow> {
+        self.windows.iter().find(|w| w.index_of(id).is_some())
+    }
+
+    pub fn window_of_mut(&mut self, id: &str) -> Option<&mut BrowserWindow> {
+        self.windows.iter_mut().find(|w| w.index_of(id).is_some())
+    }
+
+    pub fn tabs(&self) -> impl Iterator<Item = &Tab> {
+        self.windows.iter().flat_map(|w| w.tabs.iter())
+    }
+
+    pub fn get(&self, id: &str) -> Option<&Tab> {
+        self.tabs().find(|t| t.id == id)
+    }
+
+    pub fn get_mut(&mut self, id: &str) -> Option<&mut Tab> {
+        self.windows
+            .iter_mut()
+            .flat_map(|w| w.tabs.iter_mut())
+            .find(|t| t.id == id)
+    }
+
+    pub fn ids(&self) -> Vec<String> {
+        self.tabs().map(|t| t.id.clone()).collect()
+    }
+
+    pub fn is_private(&self, id: &str) -> bool {
+        self.window_of(id).is_some_and(|w| w.private)
+    }
+
+    /// ¿Queda alguna pestaña privada abierta?
+    pub fn has_private_tabs(&self) -> bool {
+        self.windows.iter().any(|w| w.private && !w.tabs.is_empty())
+    }
+
+    /// Pasa la pestaña a otra ventana, en `index` dentro de su grupo, y la
+    /// deja activa allí. Su opener se queda atrás. Devuelve la ventana de
+    /// origen. Entre privadas y normales no: cada una tiene sus datos.
+    pub fn transfer(&mut self, id: &str, to: &str, index: usize) -> Result<String, String> {
+        let target = self.window(to).ok_or("Window not found")?;
+        if target.private != self.is_private(id) {
+            return Err("Cannot move tabs between private and normal windows".to_string());
+        }
+        let from = self.window_of_mut(id).ok_or("Tab not found")?;
+        let label = from.label.clone();
+        let mut tab = from.remove(id).ok_or("Tab not found")?;
+        tab.opener = None;
+
+        let target = self.window_mut(to).ok_or("Window not found")?;
+        target.insert_at(tab, index);
+        target.activate(id);
+        Ok(label)
+    }
+}
+
+pub type TabState = Arc<Mutex<TabManager>>;
@@ -0,0 +1,283 @@
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use crate::shield::RunAt;
+
+// ================================================================
+// USER SCRIPTS — <perfil>/userscripts/*.user.js
+// ================================================================
+// Cabecera Greasemonkey (==UserScript==) con @match, @exclude,
+// @run-at y @grant none. Se inyectan por el mismo camino que los
+// módulos del shield; solo se soporta @grant none porque no hay
+// API GM_* que ofrecer.
+// ================================================================
+
+const DISABLED_FILE: &str = "disabled.json";
+
+pub struct UserScript {
+    id: String,
+    name: String,
+    matches: Vec<String>,
+    excludes: Vec<String>,
+    run_at: RunAt,
+    /// Motivo por el que no se puede inyectar (p. ej. un @grant distinto de none)
+    unsupported: Option<String>,
+    source: String,
+}
+
+#[derive(Clone, Serialize)]
+pub struct UserScriptInfo {
+    id: String,
+    name: String,
+    matches: Vec<String>,
+    excludes: Vec<String>,
+    run_at: &'static str,
+    enabled: bool,
+    unsupported: Option<String>,
+}
+
+impl UserScript {
+    fn parse(id: String, source: String) -> Self {
+        let mut script = Self {
+            name: id.clone(),
+            id,
+            matches: Vec::new(),
+            excludes: Vec::new(),
+            // Igual que Greasemonkey: document-end por defecto
+            run_at: RunAt::DocumentIdle,
+            unsupported: None,
+            source: String::new(),
+        };
+
+        let mut in_header = false;
+        for line in source.lines() {
+            let line = line.trim();
+            if line.starts_with("// ==UserScript==") {
+                in_header = true;
+                continue;
+            }
+            if line.starts_with("// ==/UserScript==") {
+                break;
+            }
+            if !in_header {
+                continue;
+            }
+            let Some(meta) = line.strip_prefix("//").map(str::trim) else {
+                continue;
+            };
+            let Some(meta) = meta.strip_prefix('@') else {
+                continue;
+            };
+            let (key, value) = match meta.split_once(char::is_whitespace) {
+                Some((k, v)) => (k, v.trim()),
+                None => (meta, ""),
+            };
+            match key {
+                "name" => script.name = value.to_string(),
+                "match" => script.matches.push(value.to_string()),
+                "exclude" => script.excludes.push(value.to_string()),
+                "run-at" => {
+                    script.run_at = if value == "document-start" {
+                        RunAt::DocumentStart
+                    } else {
+                        RunAt::DocumentIdle
+                    }
+                }
+                "grant" if value != "none" => {
+                    script.unsupported = Some(format!("Unsupported @grant {value}"));
+                }
+                _ => {}
+            }
+        }
+
+        if script.matches.is_empty() && script.unsupported.is_none() {
+            script.unsupported = Some("Missing @match".to_string());
+        }
+
+        script.source = source;
+        script
+    }
+
+    fn applies_to(&self, url: &tauri::Url) -> bool {
+        self.matches.iter().any(|p| match_pattern(p, url))
+            && !self.excludes.iter().any(|p| match_pattern(p, url))
+    }
+
+    /// Envuelto para que solo se ejecute una vez por documento: la
+    /// inyección de respaldo puede volver a evaluarlo. Y solo en el
+    /// documento principal, que es contra el que se comprueba @match: la
+    /// inyección temprana llega también a los iframes.
+    fn wrapped(&self) -> String {
+        let guard =
+            serde_json::to_string(&format!("__atomUserScript:{}", self.id)).unwrap_or_default();
+        format!(
+            "(function () {{\nif (window.top !== window || window[{guard}]) return;\nwindow[{guard}] = true;\n{}\n}})();\n",
+            self.source
+        )
+    }
+}
+
+/// Comodín `*` = cualquier secuencia de caracteres.
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let (p, t) = (pattern.as_bytes(), text.as_bytes());
+    let (mut pi, mut ti) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+
+    while ti < t.len() {
+        if pi < p.len() && p[pi] == b'*' {
+            star = Some((pi, ti));
+            pi += 1;
+        } else if pi < p.len() && p[pi] == t[ti] {
+            pi += 1;
+            ti += 1;
+        } else if let Some((sp, st)) = star {
+            pi = sp + 1;
+            ti = st + 1;
+            star = Some((sp, st + 1));
+        } else {
+            return false;
+        }
+    }
+    p[pi..].iter().all(|&c| c == b'*')
+}
+
+/// Match pattern estilo @match (`*://*.example.com/*`). Lo que no tenga
+/// esa forma se trata como glob sobre la URL completa, como @exclude
+/// en Greasemonkey.
+fn match_pattern(pattern: &str, url: &tauri::Url) -> bool {
+    if pattern == "<all_urls>" {
+        return matches!(url.scheme(), "http" | "https" | "file");
+    }
+
+    let Some((scheme, rest)) = pattern.split_once("://") else {
+        return glob_match(pattern, url.as_str());
+    };
+    let (host, path) = match rest.find('/') {
+        Some(i) => rest.split_at(i),
+        None => (rest, "/*"),
+    };
+
+    let scheme_ok = match scheme {
+        "*" => matches!(url.scheme(), "http" | "https"),
+        s => s == url.scheme(),
+    };
+
+    let url_host = url.host_str().unwrap_or_default().to_lowercase();
+    let host_ok = match host {
+        "*" => true,
+        h => match h.strip_prefix("*.") {
+            Some(base) => {
+                url_host == base
+                    || url_host
+                        .strip_suffix(base)
+                        .is_some_and(|rest| rest.ends_with('.'))
+            }
+            None => url_host == h.to_lowercase(),
+        },
+    };
+
+    let mut url_path = url.path().to_string();
+    if let Some(query) = url.query() {
+        url_path.push('?');
+        url_path.push_str(query);
+    }
+
+    scheme_ok && host_ok && glob_match(path, &url_path)
+}
+
+pub struct UserScripts {
+    dir: PathBuf,
+    scripts: Vec<UserScript>,
+    disabled: HashSet<String>,
+}
+
+impl UserScripts {
+    pub fn load(dir: PathBuf) -> Self {
+        let disabled = fs::read_to_string(dir.join(DISABLED_FILE))
+            .ok()
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default();
+        let mut user_scripts = Self {
+            dir,
+            scripts: Vec::new(),
+            disabled,
+        };
+        user_scripts.reload();
+        user_scripts
+    }
+
+    /// Vuelve a leer el directorio. Los ficheros ilegibles se ignoran.
+    pub fn reload(&mut self) {
+        let _ = fs::create_dir_all(&self.dir);
+        let mut scripts: Vec<UserScript> = fs::read_dir(&self.dir)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .filter_map(|entry| {
+                let file_name = entry.file_name().to_string_lossy().to_string();
+                if !file_name.ends_with(".user.js") {
+                    return None;
+                }
+                let source = fs::read_to_string(entry.path()).ok()?;
+                Some(UserScript::parse(file_name, source))
+            })
+            .collect();
+        scripts.sort_by(|a, b| a.id.cmp(&b.id));
+        self.scripts = scripts;
+    }
+
+    pub fn list(&self) -> Vec<UserScriptInfo> {
+        self.scripts
+            .iter()
+            .map(|s| UserScriptInfo {
+                id: s.id.clone(),
+                name: s.name.clone(),
+                matches: s.matches.clone(),
+                excludes: s.excludes.clone(),
+                run_at: match s.run_at {
+                    RunAt::DocumentStart => "document-start",
+                    RunAt::DocumentIdle => "document-idle",
+                },
+                enabled: !self.disabled.contains(&s.id),
+                unsupported: s.unsupported.clone(),
+            })
+            .collect()
+    }
+
+    pub fn set_enabled(&mut self, id: &str, enabled: bool) -> Result<(), String> {
+        if !self.scripts.iter().any(|s| s.id == id) {
+            return Err("User script not found".to_string());
+        }
+        if enabled {
+            self.disabled.remove(id);
+        } else {
+            self.disabled.insert(id.to_string());
+        }
+        let json = serde_json::to_string(&self.disabled).map_err(|e| e.to_string())?;
+        fs::write(self.dir.join(DISABLED_FILE), json).map_err(|e| e.to_string())
+    }
+
+    /// Scripts activos de `run_at` que aplican a `url`, uno por elemento:
+    /// se inyectan por separado para que un error de sintaxis en uno no
+    /// se lleve por delante a los demás ni al shield.
+    pub fn scripts_for(&self, url: &str, run_at: RunAt) -> Vec<String> {
+        let Ok(url) = tauri::Url::parse(url) else {
+            return Vec::new();
+        };
+        self.scripts
+            .iter()
+            .filter(|s| {
+                s.run_at == run_at
+                    && s.unsupported.is_none()
+                    && !self.disabled.contains(&s.id)
+                    && s.applies_to(&url)
+            })
+            .map(UserScript::wrapped)
+            .collect()
+    }
+}
+
+pub type UserScriptState = Arc<Mutex<UserScripts>>;
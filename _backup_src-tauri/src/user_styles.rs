@@ -0,0 +1,288 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use crate::shield::host_matches;
+
+// ================================================================
+// USER STYLES — <perfil>/userstyles/*.css
+// ================================================================
+// Dos formas de acotar un fichero a ciertos sitios:
+//   - Bloques UserCSS: @-moz-document domain("x"), url-prefix("y") {…}
+//   - manifest.json: [{ "file": "ancho.css", "hosts": ["docs.x.com"] }]
+//     (mismo formato de host que los módulos del shield)
+// El CSS fuera de un bloque @-moz-document, sin entrada en el
+// manifest, no se aplica a ningún sitio. Los estilos se eligen por la
+// URL del documento principal y solo se aplican ahí, no en sus iframes.
+// ================================================================
+
+const MANIFEST_FILE: &str = "manifest.json";
+
+enum Condition {
+    Domain(String),
+    Url(String),
+    UrlPrefix(String),
+}
+
+impl Condition {
+    fn matches(&self, url: &tauri::Url) -> bool {
+        match self {
+            Condition::Domain(d) => {
+                host_matches(d, &url.host_str().unwrap_or_default().to_lowercase())
+            }
+            Condition::Url(u) => url.as_str() == u,
+            Condition::UrlPrefix(p) => url.as_str().starts_with(p.as_str()),
+        }
+    }
+}
+
+enum Scope {
+    Hosts(Vec<String>),
+    Conditions(Vec<Condition>),
+}
+
+struct Section {
+    scope: Scope,
+    css: String,
+}
+
+impl Section {
+    fn applies_to(&self, url: &tauri::Url) -> bool {
+        match &self.scope {
+            Scope::Hosts(hosts) => {
+                let host = url.host_str().unwrap_or_default().to_lowercase();
+                hosts.iter().any(|h| host_matches(h, &host))
+            }
+            Scope::Conditions(conditions) => conditions.iter().any(|c| c.matches(url)),
+        }
+    }
+}
+
+struct UserStyle {
+    id: String,
+    sections: Vec<Section>,
+}
+
+#[derive(Deserialize)]
+struct ManifestEntry {
+    file: String,
+    hosts: Vec<String>,
+}
+
+#[derive(Clone, Serialize)]
+pub struct UserStyleInfo {
+    id: String,
+    sections: usize,
+}
+
+/// Índice del `}` que cierra el bloque abierto justo antes de `start`.
+/// Tiene en cuenta comentarios y cadenas para no contar llaves de más.
+fn block_end(css: &str, start: usize) -> Option<usize> {
+    let bytes = css.as_bytes();
+    let mut depth = 1;
+    let mut i = start;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'/' if bytes.get(i + 1) == Some(&b'*') => {
+                i = css[i + 2..].find("*/").map(|e| i + 2 + e + 1)?;
+            }
+            quote @ (b'"' | b'\'') => {
+                i += 1;
+                while i < bytes.len() && bytes[i] != quote {
+                    if bytes[i] == b'\\' {
+                        i += 1;
+                    }
+                    i += 1;
+                }
+            }
+            b'{' => depth += 1,
+            b'}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i);
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    None
+}
+
+/// `domain("x.com"), url-prefix(https://y/)` → condiciones. Las funciones
+/// no soportadas (p. ej. regexp) se ignoran.
+fn parse_conditions(list: &str) -> Vec<Condition> {
+    list.split(',')
+        .filter_map(|item| {
+            let (func, arg) = item.trim().split_once('(')?;
+            let arg = arg.trim_end().strip_suffix(')')?.trim();
+            let arg = arg.trim_matches(|c| c == '"' || c == '\'').to_string();
+            match func.trim() {
+                "domain" => Some(Condition::Domain(arg.to_lowercase())),
+                "url" => Some(Condition::Url(arg)),
+                "url-prefix" => Some(Condition::UrlPrefix(arg)),
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+fn parse_sections(css: &str) -> Vec<Section> {
+    const AT_RULE: &str = "@-moz-document";
+    let mut sections = Vec::new();
+    let mut rest = 0;
+
+    while let Some(found) = css[rest..].find(AT_RULE) {
+        let cond_start = rest + found + AT_RULE.len();
+        let Some(open) = css[cond_start..].find('{').map(|o| cond_start + o) else {
+            break;
+        };
+        let Some(close) = block_end(css, open + 1) else {
+            break;
+        };
+        let conditions = parse_conditions(&css[cond_start..open]);
+        if !conditions.is_empty() {
+            sections.push(Section {
+                scope: Scope::Conditions(conditions),
+                css: css[open + 1..close].trim().to_string(),
+            });
+        }
+        rest = close + 1;
+    }
+    sections
+}
+
+pub struct UserStyles {
+    dir: PathBuf,
+    styles: Vec<UserStyle>,
+}
+
+impl UserStyles {
+    pub fn load(dir: PathBuf) -> Self {
+        let mut user_styles = Self {
+            dir,
+            styles: Vec::new(),
+        };
+        user_styles.reload();
+        user_styles
+    }
+
+    pub fn dir(&self) -> PathBuf {
+        self.dir.clone()
+    }
+
+    pub fn reload(&mut self) {
+        let _ = fs::create_dir_all(&self.dir);
+
+        let manifest: HashMap<String, Vec<String>> =
+            fs::read_to_string(self.dir.join(MANIFEST_FILE))
+                .ok()
+                .and_then(|s| serde_json::from_str::<Vec<ManifestEntry>>(&s).ok())
+                .unwrap_or_default()
+                .into_iter()
+                .map(|e| (e.file, e.hosts))
+                .collect();
+
+        let mut styles: Vec<UserStyle> = fs::read_dir(&self.dir)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .filter_map(|entry| {
+                let file_name = entry.file_name().to_string_lossy().to_string();
+                if !file_name.ends_with(".css") {
+                    return None;
+                }
+                let css = fs::read_to_string(entry.path()).ok()?;
+                let sections = match manifest.get(&file_name) {
+                    Some(hosts) => vec![Section {
+                        scope: Scope::Hosts(hosts.clone()),
+                        css,
+                    }],
+                    None => parse_sections(&css),
+                };
+                Some(UserStyle {
+                    id: file_name,
+                    sections,
+                })
+            })
+            .collect();
+        styles.sort_by(|a, b| a.id.cmp(&b.id));
+        self.styles = styles;
+    }
+
+    pub fn list(&self) -> Vec<UserStyleInfo> {
+        self.styles
+            .iter()
+            .map(|s| UserStyleInfo {
+                id: s.id.clone(),
+                sections: s.sections.len(),
+            })
+            .collect()
+    }
+
+    /// Para el inicio del documento: vacío si ningún estilo aplica a `url`.
+    pub fn script_for(&self, url: &str) -> String {
+        let applicable = self.applicable(url);
+        if applicable.is_empty() {
+            return String::new();
+        }
+        apply_script(&applicable)
+    }
+
+    /// Para actualizar en caliente: deja en el documento exactamente los
+    /// estilos que aplican a `url` y retira los demás.
+    pub fn update_script_for(&self, url: &str) -> String {
+        apply_script(&self.applicable(url))
+    }
+
+    fn applicable(&self, url: &str) -> HashMap<&str, String> {
+        tauri::Url::parse(url)
+            .map(|url| {
+                self.styles
+                    .iter()
+                    .filter_map(|style| {
+                        let css: Vec<&str> = style
+                            .sections
+                            .iter()
+                            .filter(|s| s.applies_to(&url))
+                            .map(|s| s.css.as_str())
+                            .collect();
+                        (!css.is_empty()).then(|| (style.id.as_str(), css.join("\n")))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
+
+fn apply_script(applicable: &HashMap<&str, String>) -> String {
+    let styles = serde_json::to_string(applicable).unwrap_or_else(|_| "{}".to_string());
+    format!(
+        r#"(function (styles) {{
+    if (window.top !== window) return;
+    function apply() {{
+        var root = document.head || document.documentElement;
+        if (!root) return false;
+        var current = document.querySelectorAll('style[data-atom-userstyle]');
+        for (var i = 0; i < current.length; i++) {{
+            var id = current[i].getAttribute('data-atom-userstyle');
+            if (!(id in styles)) current[i].remove();
+            else {{ current[i].textContent = styles[id]; delete styles[id]; }}
+        }}
+        for (var key in styles) {{
+            var s = document.createElement('style');
+            s.setAttribute('data-atom-userstyle', key);
+            s.textContent = styles[key];
+            root.appendChild(s);
+        }}
+        return true;
+    }}
+    if (!apply()) document.addEventListener('readystatechange', apply, {{ once: true }});
+}})({styles});
+"#
+    )
+}
+
+pub type UserStyleState = Arc<Mutex<UserStyles>>;
@@ -0,0 +1,44 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+// ================================================================
+// VIGILANCIA DE DIRECTORIOS — sondeo simple
+// ================================================================
+// Basta con comparar nombre, tamaño y fecha de modificación cada
+// cierto tiempo: son directorios pequeños editados a mano.
+// ================================================================
+
+const POLL_INTERVAL: Duration = Duration::from_millis(1000);
+
+type Snapshot = Vec<(PathBuf, u64, Option<SystemTime>)>;
+
+fn snapshot(dir: &Path) -> Snapshot {
+    let mut entries: Snapshot = fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter_map(|entry| {
+            let meta = entry.metadata().ok()?;
+            Some((entry.path(), meta.len(), meta.modified().ok()))
+        })
+        .collect();
+    entries.sort();
+    entries
+}
+
+/// Llama a `on_change` (desde un hilo propio) cada vez que cambia algún
+/// fichero de `dir`, incluidas altas y bajas.
+pub fn watch_dir<F: Fn() + Send + 'static>(dir: PathBuf, on_change: F) {
+    std::thread::spawn(move || {
+        let mut last = snapshot(&dir);
+        loop {
+            std::thread::sleep(POLL_INTERVAL);
+            let current = snapshot(&dir);
+            if current != last {
+                last = current;
+                on_change();
+            }
+        }
+    });
+}
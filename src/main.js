@@ -57,6 +57,10 @@ const downloadsOverlay = document.getElementById("downloads-overlay");
 const downloadsList = document.getElementById("downloads-list");
 const btnCloseDownloads = document.getElementById("btn-close-downloads");
 const btnClearDownloads = document.getElementById("btn-clear-downloads");
+const sessionOverlay = document.getElementById("session-overlay");
+const sessionSummary = document.getElementById("session-summary");
+const btnRestoreSession = document.getElementById("btn-restore-session");
+const btnDiscardSession = document.getElementById("btn-discard-session");
 
 // Search Engine
 const searchEngineOverlay = document.getElementById("search-engine-overlay");
@@ -91,7 +95,7 @@ let currentSearchEngine = localStorage.getItem("atom-search-engine") || "duckduc
 // --- ESTADO ---
 const tabs = new Map();
 let activeTabId = null;
-const isPrivateMode = false;
+let isPrivateMode = false;
 let isAdblockEnabled = true;
 
 // --- UTILIDADES ---
@@ -103,15 +107,16 @@ function debounce(func, wait) {
   };
 }
 
-// --- FAVICON MEJORADO (Usa API de Google) ---
-function getFaviconUrl(url) {
-  try {
-    if (!url || url.startsWith('file:') || url.startsWith('atom:')) return '';
-    const urlObj = new URL(url);
-    return `https://www.google.com/s2/favicons?domain=${urlObj.hostname}&sz=64`;
-  } catch {
-    return '';
-  }
+// --- FAVICON ---
+// Lo baja la propia pestaña y lo guarda el backend: ningún servicio de
+// fuera se entera de qué sitios se visitan
+async function showFavicon(tabId, url) {
+  const fav = document.querySelector(`[data-tab-id="${tabId}"] .tab-favicon`);
+  if (!fav) return;
+  const favicon = await invoke("get_favicon", { url }).catch(() => null);
+  // Mientras tanto la pestaña pudo irse a otro sitio
+  if (tabs.get(tabId)?.url !== url) return;
+  if (favicon) { fav.src = favicon; fav.style.display = ""; } else { fav.style.display = "none"; }
 }
 
 // --- FUNCIONES AUXILIARES DE PESTAÑAS ---
@@ -242,9 +247,16 @@ function createTabElement(tabId, isActive = false) {
     if (!e.target.classList.contains("tab-close")) switchTab(tabId);
   });
 
+  // La X cierra también las fijadas; Ctrl+W no
   tabEl.querySelector(".tab-close").addEventListener("click", (e) => {
     e.stopPropagation();
-    closeTab(tabId);
+    closeTab(tabId, true);
+  });
+
+  // Clic derecho: fijar / desfijar
+  tabEl.addEventListener("contextmenu", (e) => {
+    e.preventDefault();
+    togglePin(tabId);
   });
 
   // Pointer-based drag
@@ -266,10 +278,14 @@ async function createTab(url = null) {
     const tabId = await invoke("create_tab", { url });
 
     if (tabId) {
-      tabs.set(tabId, { url: url || "about:blank", title: "Nueva pestaña" });
-      const tabEl = createTabElement(tabId, true);
-      document.querySelectorAll(".tab").forEach((t) => t.classList.remove("active"));
-      tabsContainer.insertBefore(tabEl, btnNewTab);
+      // Puede haber llegado antes el "tab-created"
+      if (!tabs.has(tabId)) {
+        tabs.set(tabId, { url: url || "about:blank", title: "Nueva pestaña" });
+        tabsContainer.insertBefore(createTabElement(tabId), btnNewTab);
+      }
+      document.querySelectorAll(".tab").forEach((t) => {
+        t.classList.toggle("active", t.dataset.tabId === tabId);
+      });
       activeTabId = tabId;
       urlInput.value = "";
       urlInput.focus();
@@ -279,24 +295,39 @@ async function createTab(url = null) {
   }
 }
 
-async function closeTab(tabId) {
+async function closeTab(tabId, force = false) {
   try {
-    await invoke("close_tab", { tabId });
+    // Sin el wrapper: si el backend se niega (fijada), la pestaña se queda
+    await electron.invoke("close_tab", { tabId, force });
     tabs.delete(tabId);
     const tabEl = document.querySelector(`[data-tab-id="${tabId}"]`);
     if (tabEl) tabEl.remove();
 
+    // La nueva activa la elige el backend y llega con tab-activated
     if (tabs.size === 0) {
       await createTab();
-    } else if (activeTabId === tabId) {
-      const firstTabId = tabs.keys().next().value;
-      await switchTab(firstTabId);
     }
   } catch (error) {
     console.error("Error cerrando pestaña:", error);
   }
 }
 
+async function togglePin(tabId) {
+  const tab = tabs.get(tabId);
+  if (!tab) return;
+  await invoke(tab.pinned ? "unpin_tab" : "pin_tab", { tabId });
+}
+
+// Las fijadas van delante: el orden lo decide el backend
+async function syncTabOrder() {
+  const list = await invoke("list_tabs");
+  if (!list) return;
+  for (const tab of list) {
+    const el = document.querySelector(`[data-tab-id="${tab.id}"]`);
+    if (el) tabsContainer.insertBefore(el, btnNewTab);
+  }
+}
+
 async function switchTab(tabId) {
   try {
     await invoke("switch_tab", { tabId });
@@ -334,9 +365,7 @@ function updateTabInfo(tabId, url) {
         const urlObj = new URL(url);
         const domain = urlObj.hostname.replace("www.", "");
         tabEl.querySelector(".tab-title").textContent = domain || "Nueva pestaña";
-        const fav = tabEl.querySelector(".tab-favicon");
-        const favUrl = getFaviconUrl(url);
-        if (favUrl) { fav.src = favUrl; fav.style.display = ""; } else { fav.style.display = "none"; }
+        showFavicon(tabId, url);
         tabEl.title = url;
       } catch {
         tabEl.querySelector(".tab-title").textContent = "Nueva pestaña";
@@ -348,13 +377,24 @@ function updateTabInfo(tabId, url) {
 }
 
 // --- NAVEGACIÓN ---
+// Si el backend la rechaza (URL no permitida, pestaña que ya no está...)
+// la barra de carga no se queda girando
+async function navigationCommand(cmd, args) {
+  try {
+    await electron.invoke(cmd, args);
+  } catch (error) {
+    stopLoading();
+    console.error(`Error en '${cmd}':`, error);
+  }
+}
+
 function handleNavigation() {
   const input = urlInput.value.trim();
   if (!input) return;
 
   let finalUrl;
   if (input.includes(".") && !input.includes(" ")) {
-    finalUrl = input.startsWith("http") ? input : "https://" + input;
+    finalUrl = /^(https?|file):/.test(input) ? input : "https://" + input;
   } else {
     const searchUrl = SEARCH_ENGINES[currentSearchEngine] || SEARCH_ENGINES.duckduckgo;
     finalUrl = searchUrl + encodeURIComponent(input);
@@ -362,38 +402,31 @@ function handleNavigation() {
 
   startLoading();
   urlInput.blur();
-  invoke("navigate", { url: finalUrl });
+  // Solo lo escrito aquí puede abrir ficheros locales
+  navigationCommand("navigate", { tabId: activeTabId, url: finalUrl, fromAddressBar: true });
 }
 
 // --- HISTORIAL ---
-const saveToHistoryDisk = debounce((history) => {
-  localStorage.setItem("atom-history", JSON.stringify(history.slice(0, 100)));
-}, 2000);
-
-function saveToHistory(url) {
-  if (isPrivateMode) return;
-  if (!url || url === "about:blank" || url.startsWith("atom://") || url.includes("home.html")) return;
-  const history = JSON.parse(localStorage.getItem("atom-history") || "[]");
-  if (history[0]?.url === url) return;
-  history.unshift({ url, time: Date.now() });
-  saveToHistoryDisk(history);
-}
-
-function renderHistory() {
-  const history = JSON.parse(localStorage.getItem("atom-history") || "[]");
+// Lo apunta el backend al terminar cada carga (nunca en ventanas privadas)
+async function renderHistory() {
+  const history = (await invoke("list_history", { limit: 200 })) || [];
   historyList.innerHTML = history.length ? "" : '<div class="empty-state">Sin historial</div>';
 
+  // URLs y títulos vienen de las páginas: solo como texto
   history.forEach(h => {
     const item = document.createElement("div");
     item.className = "history-item";
-    const date = new Date(h.time);
-    const timeStr = date.toLocaleString("es", { day: "2-digit", month: "2-digit", hour: "2-digit", minute: "2-digit" });
-    item.innerHTML = `
-      <span class="url">${h.url}</span>
-      <span class="time">${timeStr}</span>
-    `;
-    item.querySelector(".url").addEventListener("click", () => {
-      invoke("navigate", { url: h.url });
+    const date = new Date(h.last_visit);
+    const url = document.createElement("span");
+    url.className = "url";
+    url.textContent = h.url;
+    url.dataset.url = h.url;
+    const time = document.createElement("span");
+    time.className = "time";
+    time.textContent = date.toLocaleString("es", { day: "2-digit", month: "2-digit", hour: "2-digit", minute: "2-digit" });
+    item.append(url, time);
+    url.addEventListener("click", () => {
+      navigationCommand("navigate", { tabId: activeTabId, url: url.dataset.url });
       hideOverlay(historyOverlay);
     });
     historyList.appendChild(item);
@@ -432,21 +465,48 @@ listen('url-changed', (event) => {
   const { id, url } = event.payload;
   stopLoading();
   updateTabInfo(id, url);
-  saveToHistory(url);
   if (id === activeTabId) updateBookmarkStar();
 });
 
-// Pestanas creadas desde el backend (target=_blank)
-listen('tab-created-backend', (event) => {
-  const { id, url } = event.payload;
-  if (id && !tabs.has(id)) {
-    tabs.set(id, { url: url || 'about:blank', title: 'Nueva pestaña' });
-    const tabEl = createTabElement(id, true);
-    document.querySelectorAll('.tab').forEach(t => t.classList.remove('active'));
+// Pestanas creadas desde el backend (target=_blank, sesion, reabrir...)
+listen('tab-created', (event) => {
+  const { tab, active } = event.payload;
+  if (tab && !tabs.has(tab.id)) {
+    tabs.set(tab.id, { url: tab.url || 'about:blank', title: tab.title || 'Nueva pestaña', pinned: tab.pinned });
+    const tabEl = createTabElement(tab.id);
+    tabEl.classList.toggle('pinned', !!tab.pinned);
     tabsContainer.insertBefore(tabEl, btnNewTab);
-    activeTabId = id;
-    updateTabInfo(id, url);
+    updateTabInfo(tab.id, tab.url);
+    if (tab.pinned) syncTabOrder();
   }
+  if (tab && active) {
+    document.querySelectorAll('.tab').forEach(t => {
+      t.classList.toggle('active', t.dataset.tabId === tab.id);
+    });
+    activeTabId = tab.id;
+  }
+});
+
+listen('tab-updated', (event) => {
+  const tab = event.payload;
+  const info = tab && tabs.get(tab.id);
+  if (!info || !!info.pinned === tab.pinned) return;
+  info.pinned = tab.pinned;
+  const el = document.querySelector(`[data-tab-id="${tab.id}"]`);
+  if (el) el.classList.toggle('pinned', tab.pinned);
+  syncTabOrder();
+});
+
+// Activada desde el backend (buscador rápido, cierre de otra pestaña...)
+listen('tab-activated', (event) => {
+  const { tab } = event.payload;
+  if (!tab || !tabs.has(tab.id)) return;
+  activeTabId = tab.id;
+  document.querySelectorAll('.tab').forEach(t => {
+    t.classList.toggle('active', t.dataset.tabId === tab.id);
+  });
+  showDomainOnly(tab.url);
+  updateBookmarkStar();
 });
 
 // Atom Shield state sync (desde context menu del main process)
@@ -494,37 +554,40 @@ const urlSuggestions = document.getElementById("url-suggestions");
 let selectedSuggestionIndex = -1;
 let currentSuggestions = [];
 
-function getSuggestions(query) {
-  if (!query || query.length < 2) return [];
+// Pestañas, cerradas, marcadores e historial, ya ordenados por el backend
+let suggestionsRequest = 0;
 
-  const q = query.toLowerCase();
-  const results = [];
+async function getSuggestions(query) {
+  if (!query || query.length < 2) return [];
+  const request = ++suggestionsRequest;
+  const results = (await invoke("quick_switch", { query })) || [];
+  // Llegó tarde: ya se escribió otra cosa
+  if (request !== suggestionsRequest) return null;
+  return results.slice(0, 8);
+}
 
-  // Search bookmarks first (higher priority)
-  const bookmarks = getBookmarks();
-  bookmarks.forEach(b => {
-    const url = (b.url || '').toLowerCase();
-    const title = (b.title || '').toLowerCase();
-    if (url.includes(q) || title.includes(q)) {
-      results.push({ type: 'bookmark', url: b.url, title: b.title || b.url });
-    }
-  });
+const SUGGESTION_LABELS = { tab: 'Pestaña abierta', closed: 'Cerrada', bookmark: 'Marcador', history: 'Historial' };
 
-  // Then search history
-  const history = JSON.parse(localStorage.getItem("atom-history") || "[]");
-  history.forEach(h => {
-    const url = (h.url || '').toLowerCase();
-    // Avoid duplicates with bookmarks
-    if (url.includes(q) && !results.some(r => r.url === h.url)) {
-      results.push({ type: 'history', url: h.url, title: h.url });
+async function runSuggestion(s) {
+  hideSuggestions();
+  urlInput.blur();
+  const action = s.action;
+  try {
+    if (action.type === 'switch_tab') {
+      await invoke("switch_tab", { tabId: action.tab_id });
+    } else if (action.type === 'reopen') {
+      await invoke("reopen_closed_tab", { index: action.index });
+    } else {
+      urlInput.value = action.url;
+      handleNavigation();
     }
-  });
-
-  // Limit results
-  return results.slice(0, 8);
+  } catch (error) {
+    console.error("Error abriendo sugerencia:", error);
+  }
 }
 
 function renderSuggestions(suggestions) {
+  if (suggestions === null) return;
   if (suggestions.length === 0) {
     urlSuggestions.classList.add("hidden");
     return;
@@ -533,37 +596,32 @@ function renderSuggestions(suggestions) {
   currentSuggestions = suggestions;
   selectedSuggestionIndex = -1;
 
-  urlSuggestions.innerHTML = suggestions.map((s, i) => {
-    const icon = s.type === 'bookmark'
+  urlSuggestions.replaceChildren(...suggestions.map((s, i) => {
+    const item = document.createElement("div");
+    item.className = "suggestion-item";
+    item.dataset.index = i;
+    item.dataset.url = s.url;
+    // El icono es fijo; título y URL vienen de las páginas: solo como texto
+    item.innerHTML = s.kind === 'bookmark'
       ? '<svg class="suggestion-icon bookmark" width="16" height="16" viewBox="0 0 24 24" fill="currentColor"><polygon points="12 2 15.09 8.26 22 9.27 17 14.14 18.18 21.02 12 17.77 5.82 21.02 7 14.14 2 9.27 8.91 8.26 12 2"/></svg>'
       : '<svg class="suggestion-icon history" width="16" height="16" viewBox="0 0 24 24" fill="none" stroke="currentColor" stroke-width="2"><circle cx="12" cy="12" r="10"/><polyline points="12 6 12 12 16 14"/></svg>';
 
-    const displayText = s.title || s.url;
-    const typeLabel = s.type === 'bookmark' ? 'Marcador' : 'Historial';
+    const text = document.createElement("span");
+    text.className = "suggestion-text";
+    text.textContent = s.title || s.url;
+    const type = document.createElement("span");
+    type.className = "suggestion-type";
+    type.textContent = SUGGESTION_LABELS[s.kind] || 'Historial';
+    item.append(text, type);
 
-    return `
-      <div class="suggestion-item" data-index="${i}" data-url="${s.url}">
-        ${icon}
-        <span class="suggestion-text">${displayText}</span>
-        <span class="suggestion-type">${typeLabel}</span>
-      </div>
-    `;
-  }).join('');
-
-  urlSuggestions.classList.remove("hidden");
-
-  // Add click handlers
-  urlSuggestions.querySelectorAll('.suggestion-item').forEach(item => {
     item.addEventListener('mousedown', (e) => {
       e.preventDefault();
-      const url = item.dataset.url;
-      if (url) {
-        urlInput.value = url;
-        urlSuggestions.classList.add("hidden");
-        handleNavigation();
-      }
+      runSuggestion(s);
     });
-  });
+    return item;
+  }));
+
+  urlSuggestions.classList.remove("hidden");
 }
 
 function updateSelectedSuggestion() {
@@ -584,15 +642,14 @@ function hideSuggestions() {
 }
 
 // Input handler for suggestions
-const handleSuggestionsInput = debounce(() => {
+const handleSuggestionsInput = debounce(async () => {
   const query = urlInput.value.trim();
-  const suggestions = getSuggestions(query);
-  renderSuggestions(suggestions);
+  renderSuggestions(await getSuggestions(query));
 }, 150);
 
 urlInput.addEventListener('input', handleSuggestionsInput);
 
-urlInput.addEventListener('focus', () => {
+urlInput.addEventListener('focus', async () => {
   if (urlInput.dataset.fullUrl) {
     urlInput.value = urlInput.dataset.fullUrl;
     urlInput.select();
@@ -600,8 +657,7 @@ urlInput.addEventListener('focus', () => {
   // Show suggestions if there's text
   const query = urlInput.value.trim();
   if (query.length >= 2) {
-    const suggestions = getSuggestions(query);
-    renderSuggestions(suggestions);
+    renderSuggestions(await getSuggestions(query));
   }
 });
 
@@ -627,6 +683,11 @@ urlInput.addEventListener('keydown', (e) => {
     e.preventDefault();
     selectedSuggestionIndex = Math.max(selectedSuggestionIndex - 1, -1);
     updateSelectedSuggestion();
+  } else if (e.key === 'Enter' && currentSuggestions[selectedSuggestionIndex]) {
+    // Una pestaña o una cerrada no se abren navegando a su URL
+    e.preventDefault();
+    e.stopImmediatePropagation();
+    runSuggestion(currentSuggestions[selectedSuggestionIndex]);
   } else if (e.key === 'Escape') {
     hideSuggestions();
   }
@@ -647,9 +708,9 @@ btnAdblock.addEventListener("click", async () => {
 // --- EVENTOS DOM ---
 urlInput.addEventListener("keydown", (e) => { if (e.key === "Enter") handleNavigation(); });
 btnGo.addEventListener("click", handleNavigation);
-btnBack.addEventListener("click", () => invoke("go_back"));
-btnForward.addEventListener("click", () => invoke("go_forward"));
-btnRefresh.addEventListener("click", () => { startLoading(); invoke("reload"); });
+btnBack.addEventListener("click", () => navigationCommand("go_back", { tabId: activeTabId }));
+btnForward.addEventListener("click", () => navigationCommand("go_forward", { tabId: activeTabId }));
+btnRefresh.addEventListener("click", () => { startLoading(); navigationCommand("reload", { tabId: activeTabId }); });
 btnNewTab.addEventListener("click", () => {
   console.log("Click en nueva pestaña"); // Para depurar si falla
   createTab();
@@ -730,9 +791,9 @@ btnCloseSearchEngine.addEventListener("click", () => hideOverlay(searchEngineOve
 // --- ATAJOS DE TECLADO ---
 document.addEventListener("keydown", (e) => {
   if (e.ctrlKey && e.key === "l") { e.preventDefault(); urlInput.focus(); urlInput.select(); }
-  if ((e.ctrlKey && e.key === "r") || e.key === "F5") { e.preventDefault(); startLoading(); invoke("reload"); }
-  if (e.altKey && e.key === "ArrowLeft") { e.preventDefault(); invoke("go_back"); }
-  if (e.altKey && e.key === "ArrowRight") { e.preventDefault(); invoke("go_forward"); }
+  if ((e.ctrlKey && e.key === "r") || e.key === "F5") { e.preventDefault(); startLoading(); navigationCommand("reload", { tabId: activeTabId }); }
+  if (e.altKey && e.key === "ArrowLeft") { e.preventDefault(); navigationCommand("go_back", { tabId: activeTabId }); }
+  if (e.altKey && e.key === "ArrowRight") { e.preventDefault(); navigationCommand("go_forward", { tabId: activeTabId }); }
   if (e.ctrlKey && e.key === "t") { e.preventDefault(); createTab(); }
   if (e.ctrlKey && e.key === "w") { e.preventDefault(); if (activeTabId) closeTab(activeTabId); }
 
@@ -761,26 +822,33 @@ document.addEventListener("keydown", (e) => {
 });
 
 // --- MARCADORES ---
-function getBookmarks() { return JSON.parse(localStorage.getItem("atom-bookmarks") || "[]"); }
-function isBookmarked(url) { return getBookmarks().some(b => b.url === url); }
+// Los guarda el backend; aquí una copia que llega con "bookmarks-changed"
+let bookmarks = [];
+
+function getBookmarks() { return bookmarks; }
+function isBookmarked(url) { return bookmarks.some(b => b.url === url); }
 
-function toggleBookmark() {
+async function toggleBookmark() {
   const url = tabs.get(activeTabId)?.url;
   if (!url || url === "about:blank" || url.includes("home.html")) return;
 
-  let bookmarks = getBookmarks();
-  const exists = bookmarks.findIndex(b => b.url === url);
-
-  if (exists >= 0) {
-    bookmarks.splice(exists, 1);
-    btnBookmark.classList.remove("bookmarked");
-  } else {
-    bookmarks.unshift({ url, title: urlInput.value || url, time: Date.now() });
-    btnBookmark.classList.add("bookmarked");
+  try {
+    if (isBookmarked(url)) {
+      await invoke("remove_bookmark", { url });
+    } else {
+      await invoke("add_bookmark", { url });
+    }
+  } catch (error) {
+    console.error("Error guardando marcador:", error);
   }
-  localStorage.setItem("atom-bookmarks", JSON.stringify(bookmarks.slice(0, 100)));
 }
 
+listen('bookmarks-changed', (event) => {
+  bookmarks = event.payload || [];
+  updateBookmarkStar();
+  if (!bookmarksOverlay.classList.contains("hidden")) renderBookmarks();
+});
+
 function updateBookmarkStar() {
   const url = tabs.get(activeTabId)?.url;
   if (isBookmarked(url)) btnBookmark.classList.add("bookmarked");
@@ -791,23 +859,25 @@ function renderBookmarks() {
   const bookmarks = getBookmarks();
   bookmarksList.innerHTML = bookmarks.length ? "" : '<div class="empty-state">Sin marcadores</div>';
 
+  // El título viene de la página: solo como texto
   bookmarks.forEach(b => {
     const item = document.createElement("div");
     item.className = "bookmark-item";
-    item.innerHTML = `
-      <span class="url">${b.title || b.url}</span>
-      <button class="delete-btn">×</button>
-    `;
-    item.querySelector(".url").addEventListener("click", () => {
-      invoke("navigate", { url: b.url });
+    const url = document.createElement("span");
+    url.className = "url";
+    url.textContent = b.title || b.url;
+    url.dataset.url = b.url;
+    const remove = document.createElement("button");
+    remove.className = "delete-btn";
+    remove.textContent = "×";
+    item.append(url, remove);
+    url.addEventListener("click", () => {
+      navigationCommand("navigate", { tabId: activeTabId, url: url.dataset.url });
       hideOverlay(bookmarksOverlay);
     });
-    item.querySelector(".delete-btn").addEventListener("click", (e) => {
+    remove.addEventListener("click", (e) => {
       e.stopPropagation();
-      let bks = getBookmarks().filter(x => x.url !== b.url);
-      localStorage.setItem("atom-bookmarks", JSON.stringify(bks));
-      renderBookmarks();
-      updateBookmarkStar();
+      invoke("remove_bookmark", { url: url.dataset.url });
     });
     bookmarksList.appendChild(item);
   });
@@ -815,7 +885,7 @@ function renderBookmarks() {
 
 btnBookmark.addEventListener("click", toggleBookmark);
 btnCloseHistory.addEventListener("click", () => hideOverlay(historyOverlay));
-btnClearHistory.addEventListener("click", () => { localStorage.removeItem("atom-history"); renderHistory(); });
+btnClearHistory.addEventListener("click", async () => { await invoke("clear_history"); renderHistory(); });
 btnBookmark.addEventListener("contextmenu", (e) => { e.preventDefault(); renderBookmarks(); showOverlay(bookmarksOverlay); });
 btnCloseBookmarks.addEventListener("click", () => hideOverlay(bookmarksOverlay));
 
@@ -957,7 +1027,7 @@ listen('download-finished', (event) => {
     const existingIdx = history.findIndex(x => x.id === id);
     if (existingIdx >= 0) history.splice(existingIdx, 1);
     history.unshift(d);
-    saveDownloadsHistory(history);
+    if (!isPrivateMode) saveDownloadsHistory(history);
     downloads.delete(id);
     updateDownloadBtn();
     if (!downloadsOverlay.classList.contains("hidden")) renderDownloads();
@@ -966,8 +1036,33 @@ listen('download-finished', (event) => {
 
 updateDownloadBtn();
 
+// --- SESIÓN DE UN CIERRE NO LIMPIO ---
+// El backend la guarda aparte hasta que el usuario decide
+async function offerPendingSession() {
+  const pending = await invoke("get_pending_session");
+  if (!pending) return;
+  const count = pending.windows.reduce((n, w) => n + w.tabs.length, 0);
+  sessionSummary.textContent = `Atom no se cerró correctamente. ¿Restaurar ${count} ${count === 1 ? "pestaña" : "pestañas"}?`;
+  showOverlay(sessionOverlay);
+}
+
+btnRestoreSession.addEventListener("click", async () => {
+  hideOverlay(sessionOverlay);
+  await invoke("restore_pending_session");
+});
+
+btnDiscardSession.addEventListener("click", async () => {
+  hideOverlay(sessionOverlay);
+  await invoke("discard_pending_session");
+});
+
 // --- INICIALIZACIÓN ---
 async function init() {
+  // Ventana privada: sin historial ni lista de descargas
+  isPrivateMode = (await invoke("is_private_window")) === true;
+
+  bookmarks = (await invoke("list_bookmarks")) || [];
+
   // Consultar estado del adblock
   const adState = await invoke("get-adblock-state");
   isAdblockEnabled = adState !== false;
@@ -975,6 +1070,7 @@ async function init() {
   btnAdblock.title = isAdblockEnabled ? "Atom Shield: Activo" : "Atom Shield: Desactivado";
 
   await createTab();
+  if (!isPrivateMode) await offerPendingSession();
 }
 
 init();
\ No newline at end of file